@@ -1,15 +1,37 @@
+use chrono::Duration;
 use core::f64;
 use core::fmt;
+use core::ops::Add;
+use core::str::FromStr;
 use serde::Deserialize;
 
+use super::filter::{FilterReport, TickFilter};
+use super::price::Price;
+use super::utc_to_datetime;
+
+pub mod indicators;
+
 #[derive(Debug, Deserialize, Clone, Copy)]
 pub struct OHLC {
     pub open_time: Option<f64>,
-    pub open: Option<f64>,
-    pub high: Option<f64>,
-    pub low: Option<f64>,
-    pub close: Option<f64>,
+    pub open: Option<Price>,
+    pub high: Option<Price>,
+    pub low: Option<Price>,
+    pub close: Option<Price>,
     pub close_time: Option<f64>,
+    pub volume: Option<f64>,
+    pub quote_volume: Option<f64>,
+    pub buy_volume: Option<f64>,
+    pub sell_volume: Option<f64>,
+    pub vwap: Option<f64>,
+    pub trades: Option<u64>,
+    pub updates: Option<u64>,
+    pub conf_low: Option<f64>,
+    pub conf_high: Option<f64>,
+    pub conf_mean: Option<f64>,
+    // true when the candle was made up by the gap fill policy
+    #[serde(default)]
+    pub synthetic: bool,
 }
 
 impl OHLC {
@@ -21,19 +43,60 @@ impl OHLC {
             low: None,
             close: None,
             close_time: None,
+            volume: None,
+            quote_volume: None,
+            buy_volume: None,
+            sell_volume: None,
+            vwap: None,
+            trades: None,
+            updates: None,
+            conf_low: None,
+            conf_high: None,
+            conf_mean: None,
+            synthetic: false,
+        }
+    }
+    // candle made up by the gap fill policy, moving from open to close
+    pub fn synthetic(open: Price, close: Price) -> Self {
+        Self {
+            open: Some(open),
+            high: Some(open.max(close)),
+            low: Some(open.min(close)),
+            close: Some(close),
+            synthetic: true,
+            ..OHLC::new()
         }
     }
+    // moves the open to the previous close, widening high and low so they still
+    // cover it
+    pub fn reopen(&mut self, open: Price) {
+        self.open = Some(open);
+        self.high = self.high.map(|h| h.max(open));
+        self.low = self.low.map(|l| l.min(open));
+    }
     pub fn to_string(&self) -> String {
         if !self.is_valid() {
             return "Invalid Candle".to_string();
         }
-        format!(
+        let candle = format!(
             "O: {:.2}, H: {:.2}, L: {:.2}, C: {:.2}",
             self.open.unwrap(),
             self.high.unwrap(),
             self.low.unwrap(),
             self.close.unwrap()
-        )
+        );
+        let candle = match self.vwap {
+            Some(vwap) => format!("{}, VWAP: {:.4}", candle, vwap),
+            None => candle,
+        };
+        let candle = match (self.volume, self.trades) {
+            (Some(volume), Some(trades)) => format!("{}, V: {:.2}, N: {}", candle, volume, trades),
+            _ => candle,
+        };
+        match self.updates {
+            Some(updates) => format!("{}, N: {}", candle, updates),
+            None => candle,
+        }
     }
     pub fn is_valid(&self) -> bool {
         if self.open == None || self.high == None || self.low == None || self.close == None {
@@ -41,98 +104,244 @@ impl OHLC {
         }
         true
     }
-    fn twap(&self) -> Option<f64> {
+    fn twap(&self) -> Option<Price> {
         if !self.is_valid() {
             return None;
         }
-        let twap =
-            (self.open.unwrap() + self.high.unwrap() + self.low.unwrap() + self.close.unwrap())
-                / 4.0;
-        Some(twap)
+        let sum = [self.high?, self.low?, self.close?]
+            .iter()
+            .try_fold(self.open?, |sum, p| sum.checked_add(p))?;
+        // two more digits make the quarter exact
+        Some(sum.rescale(sum.expo - 2)?.div_int(4))
     }
 }
 
-// 1440 to store 1min candles for 1 full day
+// candles are stored at a fixed resolution (in seconds) starting from the
+// open time of the first slot, so the list can grow to cover any window
 pub struct CandleList {
-    candles: [OHLC; 1440],
+    start: i64,
+    resolution: i64,
+    candles: Vec<OHLC>,
+    options: CandleOptions,
+    filter_report: FilterReport,
 }
 impl CandleList {
-    pub fn new(candles: [OHLC; 1440]) -> Self {
-        Self { candles }
+    pub fn new(start: i64, resolution: i64, candles: Vec<OHLC>, options: &CandleOptions) -> Self {
+        let mut list = Self {
+            start,
+            resolution,
+            candles,
+            options: options.clone(),
+            filter_report: FilterReport::default(),
+        };
+        list.fill_gaps();
+        list
+    }
+    pub fn len(&self) -> usize {
+        self.candles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.candles.is_empty()
+    }
+    // unix timestamp (or pub_slot) of the first slot
+    pub fn start(&self) -> i64 {
+        self.start
+    }
+    // unix timestamp at the end of the last slot
+    pub fn end(&self) -> i64 {
+        self.start + self.resolution * self.candles.len() as i64
+    }
+    pub fn resolution(&self) -> i64 {
+        self.resolution
+    }
+    pub fn options(&self) -> &CandleOptions {
+        &self.options
+    }
+    // the slot that time falls in, if the list covers it
+    pub fn candle_at(&self, time: i64) -> Option<&OHLC> {
+        if time < self.start {
+            return None;
+        }
+        self.candles
+            .get(((time - self.start) / self.resolution) as usize)
+    }
+    // ticks dropped by the options filters before the candles were built
+    pub fn filter_report(&self) -> &FilterReport {
+        &self.filter_report
+    }
+    pub fn with_filter_report(mut self, report: FilterReport) -> Self {
+        self.filter_report = report;
+        self
     }
     pub fn get_candles(&self, interval: &Interval) -> Vec<OHLC> {
-        let (candle_count, size) = match interval {
-            Interval::MIN1 => (1, 1440),
-            Interval::MIN5 => (5, 288),
-            Interval::MIN15 => (15, 96),
-            Interval::HR1 => (60, 24),
-            Interval::HR4 => (240, 6),
-        };
-        let mut candles = vec![OHLC::new(); size];
+        self.group_by(interval.num_seconds())
+            .into_iter()
+            .map(|(_, c)| c)
+            .collect()
+    }
+    // same as get_candles with the open time of each candles bucket
+    pub fn get_timed_candles(&self, interval: &Interval) -> Vec<(i64, OHLC)> {
+        self.group_by(interval.num_seconds())
+    }
+    // combines every count slots, used for lists indexed by pub_slot instead of time
+    pub fn group(&self, count: usize) -> Vec<OHLC> {
+        self.group_by(count as i64 * self.resolution)
+            .into_iter()
+            .map(|(_, c)| c)
+            .collect()
+    }
+    fn group_by(&self, size: i64) -> Vec<(i64, OHLC)> {
+        let size = size.max(self.resolution);
+        let anchor = self.options.alignment.anchor(self.end(), size);
+        let key = |i: usize| (self.start + i as i64 * self.resolution - anchor).div_euclid(size);
 
-        for i in 0..candles.len() {
-            let rng = self.candles[i * candle_count..(i + 1) * candle_count].to_vec();
-            candles[i] = self.candle_smasher(rng);
+        //
+        // group slots that share a bucket, with window alignment the most recent candle
+        // is always complete and only the oldest one can be partial
+        //
+        let mut candles: Vec<(i64, OHLC)> = Vec::new();
+        let mut first = 0;
+        for i in 1..=self.candles.len() {
+            if i == self.candles.len() || key(i) != key(first) {
+                let open = anchor + key(first) * size;
+                candles.push((open, self.candle_smasher(&self.candles[first..i])));
+                first = i;
+            }
         }
 
-        // trim vector and only keep valid candles
-        let mut final_candles: Vec<OHLC> = Vec::new();
-        for (i, c) in candles.iter().enumerate() {
-            if c.is_valid() {
-                final_candles.push(*c);
+        if let GapFill::Drop = self.options.gap_fill {
+            candles.retain(|(_, c)| c.is_valid());
+        }
+        candles
+    }
+    //
+    // fills empty slots according to the gap fill policy, then makes each candles
+    // open equal the previous candles close
+    //
+    fn fill_gaps(&mut self) {
+        for i in 0..self.candles.len() {
+            if self.candles[i].is_valid() || i == 0 || !self.candles[i - 1].is_valid() {
+                continue;
+            }
+            let prev = self.candles[i - 1].close;
+            let close = match self.options.gap_fill {
+                GapFill::Empty | GapFill::Drop => continue,
+                GapFill::ForwardFill => prev,
+                GapFill::Interpolate => {
+                    //
+                    // draw a line from the last close to the next real open,
+                    // trailing gaps have nothing to aim for so they carry the close forward
+                    //
+                    let next = self.candles[i..]
+                        .iter()
+                        .enumerate()
+                        .find(|(_, c)| c.is_valid());
+                    match (prev, next) {
+                        (Some(p), Some((steps, c))) => c
+                            .open
+                            .unwrap()
+                            .checked_sub(&p)
+                            .and_then(|d| p.checked_add(&d.div_int(steps as i128 + 1)))
+                            .or(prev),
+                        _ => prev,
+                    }
+                }
+            };
+            self.candles[i] = OHLC::synthetic(prev.unwrap(), close.unwrap());
+        }
+        //
+        // next candles open price should equal prev candles close price
+        //
+        for i in 1..self.candles.len() {
+            if !self.candles[i].is_valid() {
                 continue;
             }
-            // should be setting all values to previous candles close
-            if i != 0 && candles[i - 1].is_valid() {
-                let prev = &candles[i - 1];
-                final_candles.push(OHLC {
-                    open_time: None,
-                    open: prev.close,
-                    high: prev.high,
-                    low: prev.low,
-                    close: prev.close,
-                    close_time: None,
-                })
+            if let Some(close) = self.candles[i - 1].close {
+                self.candles[i].reopen(close);
             }
         }
-        candles
     }
-    fn candle_smasher(&self, candles: Vec<OHLC>) -> OHLC {
-        if candles.len() == 0 {
+    fn candle_smasher(&self, candles: &[OHLC]) -> OHLC {
+        if candles.is_empty() {
             return OHLC::new();
         }
 
         let mut open_time: Option<f64> = None;
-        let mut open: Option<f64> = None;
-        let mut high: Option<f64> = None;
-        let mut low: Option<f64> = None;
+        let mut open: Option<Price> = None;
+        let mut high: Option<Price> = None;
+        let mut low: Option<Price> = None;
         let mut close_time: Option<f64> = None;
-        let mut close: Option<f64> = None;
+        let mut close: Option<Price> = None;
+        let mut volume: Option<f64> = None;
+        let mut quote_volume: Option<f64> = None;
+        let mut buy_volume: Option<f64> = None;
+        let mut sell_volume: Option<f64> = None;
+        let mut trades: Option<u64> = None;
+        let mut updates: Option<u64> = None;
+        let mut conf_low: Option<f64> = None;
+        let mut conf_high: Option<f64> = None;
+        let mut notional = 0.0;
+        let mut conf_sum = 0.0;
+        let mut synthetic = true;
 
         for c in candles.iter() {
             if !c.is_valid() {
                 continue;
             }
+            synthetic &= c.synthetic;
+            if let (Some(v), Some(vwap)) = (c.volume, c.vwap) {
+                notional += vwap * v;
+            }
+            if let (Some(n), Some(conf)) = (c.updates, c.conf_mean) {
+                conf_sum += conf * n as f64;
+            }
+            if c.conf_low.is_some() && (conf_low.is_none() || conf_low > c.conf_low) {
+                conf_low = c.conf_low
+            }
+            if c.conf_high.is_some() && (conf_high.is_none() || conf_high < c.conf_high) {
+                conf_high = c.conf_high
+            }
+            // counters are summed, forward filled candles dont carry any
+            volume = sum_option(volume, c.volume);
+            quote_volume = sum_option(quote_volume, c.quote_volume);
+            buy_volume = sum_option(buy_volume, c.buy_volume);
+            sell_volume = sum_option(sell_volume, c.sell_volume);
+            trades = sum_option(trades, c.trades);
+            updates = sum_option(updates, c.updates);
             if low == None || low.unwrap() > c.low.unwrap() {
                 low = c.low
             }
             if high == None || high.unwrap() < c.high.unwrap() {
                 high = c.high
             }
-            if c.open_time != None
-                && (open_time == None || open_time.unwrap() > c.open_time.unwrap())
-            {
-                open_time = c.open_time;
+            //
+            // candles are in order so the first valid candle opens and the last one closes,
+            // synthetic candles have no times of their own
+            //
+            if open.is_none() {
                 open = c.open;
             }
-            if c.close_time != None
-                && (close_time == None || close_time.unwrap() < c.close_time.unwrap())
-            {
+            if open_time.is_none() {
+                open_time = c.open_time;
+            }
+            close = c.close;
+            if c.close_time.is_some() {
                 close_time = c.close_time;
-                close = c.close;
             }
         }
 
+        // combined vwap is the volume weighted mean of each candles vwap
+        let vwap = match volume {
+            Some(v) if v > 0.0 => Some(notional / v),
+            _ => None,
+        };
+
+        // mean confidence is weighted by the number of updates in each candle
+        let conf_mean = match updates {
+            Some(n) if conf_low.is_some() && n > 0 => Some(conf_sum / n as f64),
+            _ => None,
+        };
+
         OHLC {
             open_time,
             open,
@@ -140,41 +349,715 @@ impl CandleList {
             low,
             close,
             close_time,
+            volume,
+            quote_volume,
+            buy_volume,
+            sell_volume,
+            vwap,
+            trades,
+            updates,
+            conf_low,
+            conf_high,
+            conf_mean,
+            synthetic: synthetic && open.is_some(),
         }
     }
-    pub fn twap(&self, interval: &Interval) -> Option<f64> {
-        let candles = self.get_candles(&interval);
-        let mut twap = 0.0;
-        let mut counter = 0.0;
+    // end is the unix timestamp the query ran up to, staleness is measured from it
+    pub fn twap(&self, interval: &Interval, method: &TwapMethod, end: i64) -> TwapResult {
+        let candles = self.get_candles(interval);
+        let mut prices = Vec::new();
+        let mut synthetic = 0;
         for c in candles.iter() {
             if c.is_valid() {
-                twap += c.twap().unwrap();
-                counter += 1.0;
+                prices.push(c.twap().unwrap());
+                if c.synthetic {
+                    synthetic += 1;
+                }
+            }
+        }
+        //
+        // tick coverage comes from the slots the list was built from, synthetic
+        // candles have no ticks of their own so they only widen the gaps
+        //
+        let mut ticks = 0;
+        let mut first_tick: Option<f64> = None;
+        let mut last_tick: Option<f64> = None;
+        let mut largest_gap = 0.0;
+        for c in self.candles.iter().filter(|c| c.is_valid() && !c.synthetic) {
+            ticks += c.updates.unwrap_or(0) + c.trades.unwrap_or(0);
+            if let (Some(last), Some(open)) = (last_tick, c.open_time) {
+                largest_gap = f64::max(largest_gap, open - last);
+            }
+            if first_tick.is_none() {
+                first_tick = c.open_time;
+            }
+            if c.close_time.is_some() {
+                last_tick = c.close_time;
+            }
+        }
+        TwapResult {
+            value: method.average(&prices),
+            method: *method,
+            candles: prices.len(),
+            synthetic,
+            ticks,
+            first_tick,
+            last_tick,
+            largest_gap,
+            staleness: last_tick.map(|t| (end as f64 - t).max(0.0)),
+        }
+    }
+    //
+    // twap and vwap over the trailing window candles at each candle, for charting how
+    // the average drifts. points are keyed by the open time of their latest candle and
+    // the first window - 1 points cover fewer candles
+    //
+    pub fn rolling(
+        &self,
+        interval: &Interval,
+        window: usize,
+        method: &TwapMethod,
+    ) -> Vec<RollingPoint> {
+        let candles = self.get_timed_candles(interval);
+        let window = window.max(1);
+        let mut points = Vec::with_capacity(candles.len());
+        for i in 0..candles.len() {
+            let trailing = &candles[(i + 1).saturating_sub(window)..=i];
+            let prices: Vec<Price> = trailing.iter().filter_map(|(_, c)| c.twap()).collect();
+            let mut volume = 0.0;
+            let mut notional = 0.0;
+            for (_, c) in trailing.iter() {
+                if let (Some(v), Some(vwap)) = (c.volume, c.vwap) {
+                    volume += v;
+                    notional += vwap * v;
+                }
+            }
+            points.push(RollingPoint {
+                time: candles[i].0,
+                twap: method.average(&prices),
+                vwap: if volume > 0.0 {
+                    Some(notional / volume)
+                } else {
+                    None
+                },
+                candles: prices.len(),
+            });
+        }
+        points
+    }
+    //
+    // annualized realized volatility of the interval candles. only candles with real
+    // ticks are used since gap filled candles have no range and would pull it down
+    //
+    pub fn volatility(&self, interval: &Interval, estimator: &Volatility) -> Option<f64> {
+        let candles: Vec<[f64; 4]> = self
+            .get_candles(interval)
+            .iter()
+            .filter(|c| c.is_valid() && !c.synthetic && c.low.unwrap().is_positive())
+            .map(|c| [c.open, c.high, c.low, c.close].map(|p| p.unwrap().to_f64()))
+            .collect();
+        let variance = match estimator {
+            Volatility::CloseToClose => {
+                // sample variance of log returns between candle closes
+                if candles.len() < 3 {
+                    return None;
+                }
+                let returns: Vec<f64> = candles
+                    .windows(2)
+                    .map(|w| (w[1][3] / w[0][3]).ln())
+                    .collect();
+                let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+                let sum: f64 = returns.iter().map(|r| (r - mean).powi(2)).sum();
+                Some(sum / (returns.len() - 1) as f64)
+            }
+            Volatility::Parkinson => mean_of(&candles, |[_, h, l, _]| {
+                (h / l).ln().powi(2) / (4.0 * 2f64.ln())
+            }),
+            Volatility::GarmanKlass => mean_of(&candles, |[o, h, l, c]| {
+                0.5 * (h / l).ln().powi(2) - (2.0 * 2f64.ln() - 1.0) * (c / o).ln().powi(2)
+            }),
+            Volatility::RogersSatchell => mean_of(&candles, |[o, h, l, c]| {
+                (h / c).ln() * (h / o).ln() + (l / c).ln() * (l / o).ln()
+            }),
+        }?;
+        let periods = YEAR as f64 / interval.num_seconds() as f64;
+        Some((variance.max(0.0) * periods).sqrt())
+    }
+    //
+    // price distribution over the resolution slots of the list, e.g. the per minute
+    // buckets. percentiles use each slots (O+H+L+C)/4 so every slot gets the same time
+    // weight, and a slot counts as above or below twap by that price too. gap filled
+    // slots are left out, none when no slot had ticks
+    //
+    pub fn stats(&self, twap: &Price) -> Option<PriceStats> {
+        let candles: Vec<&OHLC> = self
+            .candles
+            .iter()
+            .filter(|c| c.is_valid() && !c.synthetic)
+            .collect();
+        let mut prices: Vec<Price> = candles.iter().filter_map(|c| c.twap()).collect();
+        if prices.is_empty() {
+            return None;
+        }
+        prices.sort();
+        // nearest rank, so each percentile is a price one of the slots had
+        let percentile = |pct: usize| prices[(pct * prices.len()).div_ceil(100).max(1) - 1];
+
+        let mut seen: Vec<Price> = candles
+            .iter()
+            .flat_map(|c| [c.open, c.high, c.low, c.close])
+            .flatten()
+            .collect();
+        seen.sort();
+        seen.dedup();
+
+        Some(PriceStats {
+            min: candles.iter().filter_map(|c| c.low).min().unwrap(),
+            max: candles.iter().filter_map(|c| c.high).max().unwrap(),
+            percentiles: [5, 25, 50, 75, 95].map(|pct| (pct, percentile(pct))),
+            above: prices.iter().filter(|p| *p > twap).count() as i64 * self.resolution,
+            below: prices.iter().filter(|p| *p < twap).count() as i64 * self.resolution,
+            distinct_ohlc: seen.len(),
+            distinct_ticks: None,
+            slots: prices.len(),
+        })
+    }
+}
+#[derive(Debug, Clone, Copy)]
+pub struct PriceStats {
+    pub min: Price,
+    pub max: Price,
+    // (percent, price) for p5, p25, p50, p75 and p95
+    pub percentiles: [(usize, Price); 5],
+    // seconds in slots priced above and below the twap
+    pub above: i64,
+    pub below: i64,
+    // distinct opens, highs, lows and closes across the slots, fewer than the ticks had
+    pub distinct_ohlc: usize,
+    // distinct tick prices, when the ticks the list was built from are at hand
+    pub distinct_ticks: Option<usize>,
+    // slots with ticks
+    pub slots: usize,
+}
+impl PriceStats {
+    pub fn with_distinct_ticks(mut self, distinct: usize) -> Self {
+        self.distinct_ticks = Some(distinct);
+        self
+    }
+}
+impl fmt::Display for PriceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "min {:.4}, max {:.4}", self.min, self.max)?;
+        for (pct, price) in self.percentiles.iter() {
+            write!(f, ", p{} {:.4}", pct, price)?;
+        }
+        write!(f, ", {}s above and {}s below TWAP", self.above, self.below)?;
+        match self.distinct_ticks {
+            Some(n) => write!(f, ", {} distinct tick prices", n)?,
+            None => write!(f, ", {} distinct OHLC prices", self.distinct_ohlc)?,
+        }
+        write!(f, " over {} slots", self.slots)
+    }
+}
+// one point of a rolling average series
+#[derive(Debug, Clone, Copy)]
+pub struct RollingPoint {
+    // unix timestamp the latest candle in the window opened at
+    pub time: i64,
+    pub twap: Option<Price>,
+    pub vwap: Option<f64>,
+    // valid candles in the window
+    pub candles: usize,
+}
+impl fmt::Display for RollingPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", utc_to_datetime(self.time))?;
+        match self.twap {
+            Some(twap) => write!(f, ", TWAP: {:.4}", twap)?,
+            None => write!(f, ", TWAP: -")?,
+        }
+        if let Some(vwap) = self.vwap {
+            write!(f, ", VWAP: {:.4}", vwap)?;
+        }
+        write!(f, ", N: {}", self.candles)
+    }
+}
+//
+// average over the candles along with how much real data went into it, so callers
+// can reject a twap built mostly from gap filled candles or old ticks
+//
+#[derive(Debug, Clone, Copy)]
+pub struct TwapResult {
+    // none when there were no valid candles
+    pub value: Option<Price>,
+    pub method: TwapMethod,
+    pub candles: usize,
+    // candles made up by the gap fill policy
+    pub synthetic: usize,
+    pub ticks: u64,
+    // unix timestamps of the first and last tick
+    pub first_tick: Option<f64>,
+    pub last_tick: Option<f64>,
+    // longest stretch in seconds between two ticks
+    pub largest_gap: f64,
+    // seconds from the last tick to the end of the query
+    pub staleness: Option<f64>,
+}
+impl TwapResult {
+    // candles with at least one tick
+    pub fn real(&self) -> usize {
+        self.candles - self.synthetic
+    }
+}
+
+//
+// how the (O+H+L+C)/4 price of each candle is averaged. every candle covers the
+// same interval so each one gets the same time weight
+//
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TwapMethod {
+    Mean,
+    // middle candle price, not moved by a few extreme candles
+    Median,
+    // mean after dropping this percent of candles from both the top and bottom
+    TrimmedMean(f64),
+    // for inverse quoted pairs, the mean of 1/price inverted back
+    Harmonic,
+}
+impl TwapMethod {
+    pub fn average(&self, prices: &[Price]) -> Option<Price> {
+        if prices.is_empty() {
+            return None;
+        }
+        let mut sorted = prices.to_vec();
+        sorted.sort();
+        match self {
+            TwapMethod::Mean => Price::mean(prices),
+            TwapMethod::Median => {
+                let mid = sorted.len() / 2;
+                if sorted.len().is_multiple_of(2) {
+                    let sum = sorted[mid - 1].checked_add(&sorted[mid])?;
+                    Some(sum.rescale(sum.expo - 1)?.div_int(2))
+                } else {
+                    Some(sorted[mid])
+                }
+            }
+            TwapMethod::TrimmedMean(pct) => {
+                // always keep at least the middle candle
+                let trim = (sorted.len() as f64 * pct.clamp(0.0, 50.0) / 100.0).floor() as usize;
+                let trim = trim.min((sorted.len() - 1) / 2);
+                Price::mean(&sorted[trim..sorted.len() - trim])
+            }
+            TwapMethod::Harmonic => {
+                if !sorted[0].is_positive() {
+                    return None;
+                }
+                // 1 / mean of the inverses, averaging first keeps the candle count
+                // out of the final division so it cant overflow with many candles
+                let one = Price::new(1, 0);
+                let inverse = prices
+                    .iter()
+                    .try_fold(Price::new(0, HARMONIC_EXPO), |sum, p| {
+                        sum.checked_add(&one.checked_div(p, HARMONIC_EXPO)?)
+                    })?
+                    .div_int(prices.len() as i128);
+                let expo = prices.iter().map(|p| p.expo).min()?;
+                one.checked_div(&inverse, expo)
             }
         }
-        let twap = twap / counter;
-        Some(twap)
     }
 }
+// inverses for the harmonic mean keep this many digits so small prices stay exact
+const HARMONIC_EXPO: i32 = -18;
+impl fmt::Display for TwapMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TwapMethod::Mean => write!(f, "mean"),
+            TwapMethod::Median => write!(f, "median"),
+            TwapMethod::TrimmedMean(pct) => write!(f, "{}% trimmed mean", pct),
+            TwapMethod::Harmonic => write!(f, "harmonic mean"),
+        }
+    }
+}
+// realized volatility estimators, the range based ones use more of each candle than
+// its close and need fewer candles for the same accuracy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Volatility {
+    // standard deviation of log returns between closes
+    CloseToClose,
+    // high/low range, assumes no drift
+    Parkinson,
+    // open, high, low and close, assumes no drift
+    GarmanKlass,
+    // open, high, low and close, allows for drift
+    RogersSatchell,
+}
+impl fmt::Display for Volatility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Volatility::CloseToClose => write!(f, "close to close"),
+            Volatility::Parkinson => write!(f, "Parkinson"),
+            Volatility::GarmanKlass => write!(f, "Garman-Klass"),
+            Volatility::RogersSatchell => write!(f, "Rogers-Satchell"),
+        }
+    }
+}
+// mean of f over the [open, high, low, close] candles
+fn mean_of(candles: &[[f64; 4]], f: impl Fn([f64; 4]) -> f64) -> Option<f64> {
+    if candles.is_empty() {
+        return None;
+    }
+    Some(candles.iter().map(|c| f(*c)).sum::<f64>() / candles.len() as f64)
+}
+fn sum_option<T: Add<Output = T>>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (Some(x), None) | (None, Some(x)) => Some(x),
+        (None, None) => None,
+    }
+}
+// how to handle slots that had no ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GapFill {
+    // repeat the previous close
+    ForwardFill,
+    // leave the candle empty so it is skipped
+    Empty,
+    // step linearly from the previous close to the next open
+    Interpolate,
+    // remove empty candles from get_candles
+    Drop,
+}
+impl fmt::Display for GapFill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GapFill::ForwardFill => write!(f, "forward fill"),
+            GapFill::Empty => write!(f, "leave empty"),
+            GapFill::Interpolate => write!(f, "interpolate"),
+            GapFill::Drop => write!(f, "drop"),
+        }
+    }
+}
+// where candle boundaries fall
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    // count back from the end of the window so the last candle is complete
+    Window,
+    // snap to multiples of the interval since the unix epoch, weeks start on monday
+    Epoch,
+}
+impl Alignment {
+    // a timestamp that falls on a candle boundary for the given size
+    pub fn anchor(&self, end: i64, size: i64) -> i64 {
+        match self {
+            Alignment::Window => end,
+            // 1970-01-05 was the first monday after the epoch
+            Alignment::Epoch if size % WEEK == 0 => 4 * DAY,
+            Alignment::Epoch => 0,
+        }
+    }
+}
+impl fmt::Display for Alignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alignment::Window => write!(f, "end of window"),
+            Alignment::Epoch => write!(f, "UTC clock"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CandleOptions {
+    pub gap_fill: GapFill,
+    pub alignment: Alignment,
+    // size of the slots ticks are sorted into before they are grouped by get_candles
+    pub resolution: Interval,
+    // bad tick checks run before any candle is built
+    pub filters: Vec<TickFilter>,
+}
+impl Default for CandleOptions {
+    fn default() -> Self {
+        Self {
+            gap_fill: GapFill::ForwardFill,
+            alignment: Alignment::Window,
+            resolution: Interval::MIN1,
+            filters: Vec::new(),
+        }
+    }
+}
+
+//
+// sorts ticks into slots of resolution seconds ending at end, returns the open time of
+// the first slot. ticks at or after end are ignored, with epoch alignment the last
+// slot can run past end but only holds ticks from before it
+//
+pub(crate) fn bucket_ticks<T: Clone>(
+    ticks: &[T],
+    time: impl Fn(&T) -> i64,
+    end: i64,
+    resolution: i64,
+    options: &CandleOptions,
+) -> (i64, Vec<Vec<T>>) {
+    let anchor = options.alignment.anchor(end, resolution);
+    let slot = |t: i64| (t - anchor).div_euclid(resolution);
+    // the slot holding the last moment before end
+    let last = slot(end - 1);
+    let first = ticks
+        .iter()
+        .filter(|t| time(t) < end)
+        .map(|t| slot(time(t)))
+        .min()
+        .unwrap_or(last + 1);
+
+    let mut buckets: Vec<Vec<T>> = vec![Vec::new(); (last + 1 - first) as usize];
+    for t in ticks.iter().filter(|t| time(t) < end) {
+        buckets[(slot(time(t)) - first) as usize].push(t.clone());
+    }
+    (anchor + first * resolution, buckets)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Interval {
+    SEC1,
+    SEC5,
+    SEC10,
+    SEC30,
     MIN1,
     MIN5,
     MIN15,
+    MIN30,
     HR1,
+    HR2,
     HR4,
+    DAY1,
+    WEEK1,
+    Custom(Duration),
 }
 impl fmt::Display for Interval {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Interval::SEC1 => write!(f, "1 second"),
+            Interval::SEC5 => write!(f, "5 second"),
+            Interval::SEC10 => write!(f, "10 second"),
+            Interval::SEC30 => write!(f, "30 second"),
             Interval::MIN1 => write!(f, "1 minute"),
             Interval::MIN5 => write!(f, "5 minute"),
             Interval::MIN15 => write!(f, "15 minute"),
+            Interval::MIN30 => write!(f, "30 minute"),
             Interval::HR1 => write!(f, "1 hour"),
+            Interval::HR2 => write!(f, "2 hour"),
             Interval::HR4 => write!(f, "4 hour"),
+            Interval::DAY1 => write!(f, "1 day"),
+            Interval::WEEK1 => write!(f, "1 week"),
+            Interval::Custom(d) => {
+                // use the largest unit that divides the duration evenly
+                let secs = d.num_seconds();
+                for (unit, size) in INTERVAL_UNITS.iter().rev() {
+                    if secs % size == 0 {
+                        return write!(f, "{} {}", secs / size, unit);
+                    }
+                }
+                write!(f, "{} second", secs)
+            }
+        }
+    }
+}
+impl Interval {
+    pub fn num_seconds(&self) -> i64 {
+        match self {
+            Interval::SEC1 => 1,
+            Interval::SEC5 => 5,
+            Interval::SEC10 => 10,
+            Interval::SEC30 => 30,
+            Interval::MIN1 => MINUTE,
+            Interval::MIN5 => 5 * MINUTE,
+            Interval::MIN15 => 15 * MINUTE,
+            Interval::MIN30 => 30 * MINUTE,
+            Interval::HR1 => HOUR,
+            Interval::HR2 => 2 * HOUR,
+            Interval::HR4 => 4 * HOUR,
+            Interval::DAY1 => DAY,
+            Interval::WEEK1 => WEEK,
+            Interval::Custom(d) => d.num_seconds(),
+        }
+    }
+    pub fn duration(&self) -> Duration {
+        Duration::seconds(self.num_seconds())
+    }
+    // named intervals are used when the duration matches one. candles need at least
+    // a second to bucket ticks into
+    pub fn from_duration(d: Duration) -> Result<Self, &'static str> {
+        if d.num_seconds() <= 0 {
+            return Err("interval needs to be at least one second");
+        }
+        let named = [
+            Interval::SEC1,
+            Interval::SEC5,
+            Interval::SEC10,
+            Interval::SEC30,
+            Interval::MIN1,
+            Interval::MIN5,
+            Interval::MIN15,
+            Interval::MIN30,
+            Interval::HR1,
+            Interval::HR2,
+            Interval::HR4,
+            Interval::DAY1,
+            Interval::WEEK1,
+        ];
+        match named.iter().find(|i| i.num_seconds() == d.num_seconds()) {
+            Some(i) => Ok(*i),
+            None => Ok(Interval::Custom(d)),
+        }
+    }
+}
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+const WEEK: i64 = 7 * DAY;
+// crypto trades every day of the year
+const YEAR: i64 = 365 * DAY;
+const INTERVAL_UNITS: [(&str, i64); 5] = [
+    ("second", 1),
+    ("minute", MINUTE),
+    ("hour", HOUR),
+    ("day", DAY),
+    ("week", WEEK),
+];
+// parses strings like "10s", "30m", "2h", "1d", "1w" or "15 minute"
+impl FromStr for Interval {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (count, unit) = s.split_at(split);
+        let count: i64 = match count.parse() {
+            Ok(i) if i > 0 => i,
+            // only digits were split off, so a parse error past this is an overflow
+            Err(_) if !count.is_empty() => return Err("interval is too long"),
+            _ => return Err("interval needs a positive number, e.g. 30m"),
+        };
+        // plurals are listed so units like "ms" arent read as minutes
+        let size = match unit.trim() {
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            "m" | "min" | "mins" | "minute" | "minutes" => MINUTE,
+            "h" | "hr" | "hrs" | "hour" | "hours" => HOUR,
+            "d" | "day" | "days" => DAY,
+            "w" | "wk" | "wks" | "week" | "weeks" => WEEK,
+            _ => return Err("unknown interval unit, use s, m, h, d or w"),
+        };
+        // Duration::seconds panics past its millisecond range
+        match count.checked_mul(size) {
+            Some(secs) if secs <= Duration::max_value().num_seconds() => {
+                Interval::from_duration(Duration::seconds(secs))
+            }
+            _ => Err("interval is too long"),
         }
     }
 }
 
+// time weighted average of (unix seconds, price) ticks over [start, end)
+// each price is weighted by how long it stayed in effect before the next tick,
+// the last tick before the window carries into it. times are weighed in whole
+// milliseconds so the sum stays fixed point, none if it overflows
+pub fn time_weighted_average(ticks: &[(f64, Price)], start: f64, end: f64) -> Option<Price> {
+    let mut ticks = ticks.to_vec();
+    ticks.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+    let mut weighted: Option<Price> = None;
+    let mut elapsed: i128 = 0;
+    for (i, (time, price)) in ticks.iter().enumerate() {
+        let from = time.max(start);
+        let to = match ticks.get(i + 1) {
+            Some(next) => next.0.min(end),
+            None => end,
+        };
+        let millis = ((to - from) * 1000.0).round() as i128;
+        if millis <= 0 {
+            continue;
+        }
+        let part = Price::new(price.mantissa.checked_mul(millis)?, price.expo);
+        weighted = Some(match weighted {
+            Some(sum) => sum.checked_add(&part)?,
+            None => part,
+        });
+        elapsed += millis;
+    }
+    Some(weighted?.div_int(elapsed))
+}
+
+// true time weighted price next to the (O+H+L+C)/4 candle estimate
+#[derive(Debug, Clone, Copy)]
+pub struct TwapEstimate {
+    pub time_weighted: Option<Price>,
+    pub ohlc4: TwapResult,
+}
+impl TwapEstimate {
+    // how far the candle estimate is from the time weighted price
+    pub fn difference(&self) -> Option<f64> {
+        match (self.ohlc4.value, self.time_weighted) {
+            (Some(ohlc4), Some(time_weighted)) => Some(ohlc4.checked_sub(&time_weighted)?.to_f64()),
+            _ => None,
+        }
+    }
+}
+
+pub fn print_rolling(points: &[RollingPoint]) {
+    for p in points.iter() {
+        println!("{}", p);
+    }
+}
+
+// time,twap,vwap,candles with empty cells for missing averages
+pub fn rolling_to_csv(points: &[RollingPoint]) -> String {
+    let mut csv = String::from("time,twap,vwap,candles\n");
+    for p in points.iter() {
+        let twap = p.twap.map(|v| v.to_string()).unwrap_or_default();
+        let vwap = p.vwap.map(|v| v.to_string()).unwrap_or_default();
+        csv += &format!("{},{},{},{}\n", p.time, twap, vwap, p.candles);
+    }
+    csv
+}
+
+//
+// heikin-ashi candles smooth out noise: close is the candles (O+H+L+C)/4 and open is
+// the midpoint of the previous heikin-ashi candle. empty candles are kept as they are
+//
+pub fn heikin_ashi(candles: &[OHLC]) -> Vec<OHLC> {
+    let mut prev: Option<OHLC> = None;
+    let mut out = Vec::with_capacity(candles.len());
+    for c in candles.iter() {
+        if !c.is_valid() {
+            out.push(*c);
+            continue;
+        }
+        let close = c.twap().unwrap();
+        let mid = match prev {
+            Some(p) => p.open.unwrap().checked_add(&p.close.unwrap()),
+            None => c.open.unwrap().checked_add(&c.close.unwrap()),
+        };
+        // rounded at the close exponent so the digits dont grow with every candle
+        let open = match mid.and_then(|mid| mid.rescale(close.expo)) {
+            Some(mid) => mid.div_int(2),
+            None => {
+                out.push(*c);
+                continue;
+            }
+        };
+        let candle = OHLC {
+            open: Some(open),
+            high: Some(c.high.unwrap().max(open).max(close)),
+            low: Some(c.low.unwrap().min(open).min(close)),
+            close: Some(close),
+            ..*c
+        };
+        prev = Some(candle);
+        out.push(candle);
+    }
+    out
+}
+
 pub fn print_candles(candles: &Vec<OHLC>) {
     for (i, c) in candles.iter().enumerate() {
         if c.is_valid() {
@@ -0,0 +1,231 @@
+use super::{CandleList, Interval};
+
+//
+// indicators over the candles from CandleList::get_candles. each point carries the
+// index of its candle in that list and the open time of its bucket. empty candles
+// are skipped so a point only exists once enough valid candles came before it
+//
+
+#[derive(Debug, Clone, Copy)]
+pub struct Point<T> {
+    pub index: usize,
+    pub time: i64,
+    pub value: T,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Macd {
+    pub macd: f64,
+    pub signal: f64,
+    pub histogram: f64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Bands {
+    pub lower: f64,
+    pub middle: f64,
+    pub upper: f64,
+}
+
+// a valid candle with its index and bucket open time
+struct Bar {
+    index: usize,
+    time: i64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+fn bars(list: &CandleList, interval: &Interval) -> Vec<Bar> {
+    list.get_timed_candles(interval)
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, c))| c.is_valid())
+        .map(|(index, (time, c))| Bar {
+            index,
+            time: *time,
+            high: c.high.unwrap().to_f64(),
+            low: c.low.unwrap().to_f64(),
+            close: c.close.unwrap().to_f64(),
+        })
+        .collect()
+}
+
+fn points<T>(bars: &[Bar], values: Vec<Option<T>>) -> Vec<Point<T>> {
+    bars.iter()
+        .zip(values)
+        .filter_map(|(b, v)| {
+            Some(Point {
+                index: b.index,
+                time: b.time,
+                value: v?,
+            })
+        })
+        .collect()
+}
+
+// simple moving average over the last period values
+fn sma_of(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let mut out = vec![None; values.len()];
+    let mut sum = 0.0;
+    for i in 0..values.len() {
+        sum += values[i];
+        if i >= period {
+            sum -= values[i - period];
+        }
+        if i + 1 >= period {
+            out[i] = Some(sum / period as f64);
+        }
+    }
+    out
+}
+
+// ema with smoothing 2/(period+1), seeded with the sma of the first period values
+fn ema_of(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let alpha = 2.0 / (period as f64 + 1.0);
+    let mut out = vec![None; values.len()];
+    let mut ema: Option<f64> = None;
+    for i in 0..values.len() {
+        ema = match ema {
+            Some(e) => Some(e + alpha * (values[i] - e)),
+            None if i + 1 == period => Some(values[..period].iter().sum::<f64>() / period as f64),
+            None => None,
+        };
+        out[i] = ema;
+    }
+    out
+}
+
+// wilder smoothing, ema with smoothing 1/period as used by rsi and atr
+fn wilder_of(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let period = period.max(1);
+    let mut out = vec![None; values.len()];
+    let mut avg: Option<f64> = None;
+    for i in 0..values.len() {
+        avg = match avg {
+            Some(a) => Some((a * (period - 1) as f64 + values[i]) / period as f64),
+            None if i + 1 == period => Some(values[..period].iter().sum::<f64>() / period as f64),
+            None => None,
+        };
+        out[i] = avg;
+    }
+    out
+}
+
+pub fn sma(list: &CandleList, interval: &Interval, period: usize) -> Vec<Point<f64>> {
+    let bars = bars(list, interval);
+    let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+    points(&bars, sma_of(&closes, period))
+}
+
+pub fn ema(list: &CandleList, interval: &Interval, period: usize) -> Vec<Point<f64>> {
+    let bars = bars(list, interval);
+    let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+    points(&bars, ema_of(&closes, period))
+}
+
+// relative strength index from 0 to 100 using wilder smoothed gains and losses
+pub fn rsi(list: &CandleList, interval: &Interval, period: usize) -> Vec<Point<f64>> {
+    let bars = bars(list, interval);
+    if bars.len() < 2 {
+        return Vec::new();
+    }
+    let changes: Vec<f64> = bars.windows(2).map(|w| w[1].close - w[0].close).collect();
+    let gains: Vec<f64> = changes.iter().map(|c| c.max(0.0)).collect();
+    let losses: Vec<f64> = changes.iter().map(|c| (-c).max(0.0)).collect();
+    let values = wilder_of(&gains, period)
+        .into_iter()
+        .zip(wilder_of(&losses, period))
+        .map(|(gain, loss)| {
+            let (gain, loss) = (gain?, loss?);
+            if loss == 0.0 {
+                return Some(100.0);
+            }
+            Some(100.0 - 100.0 / (1.0 + gain / loss))
+        })
+        .collect();
+    // the first candle has no change before it
+    points(&bars[1..], values)
+}
+
+// fast ema - slow ema, with an ema of that as the signal line
+pub fn macd(
+    list: &CandleList,
+    interval: &Interval,
+    fast: usize,
+    slow: usize,
+    signal: usize,
+) -> Vec<Point<Macd>> {
+    let bars = bars(list, interval);
+    let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+    let lines: Vec<Option<f64>> = ema_of(&closes, fast)
+        .into_iter()
+        .zip(ema_of(&closes, slow))
+        .map(|(f, s)| Some(f? - s?))
+        .collect();
+
+    // the signal line starts once the macd line has signal values
+    let first = lines
+        .iter()
+        .position(|l| l.is_some())
+        .unwrap_or(lines.len());
+    let defined: Vec<f64> = lines[first..].iter().map(|l| l.unwrap()).collect();
+    let signals = ema_of(&defined, signal);
+    let mut values = vec![None; first];
+    for (line, signal) in defined.iter().zip(signals) {
+        values.push(signal.map(|signal| Macd {
+            macd: *line,
+            signal,
+            histogram: line - signal,
+        }));
+    }
+    points(&bars, values)
+}
+
+// sma of the closes with bands k population standard deviations above and below
+pub fn bollinger(
+    list: &CandleList,
+    interval: &Interval,
+    period: usize,
+    k: f64,
+) -> Vec<Point<Bands>> {
+    let bars = bars(list, interval);
+    let closes: Vec<f64> = bars.iter().map(|b| b.close).collect();
+    let period = period.max(1);
+    let values = sma_of(&closes, period)
+        .into_iter()
+        .enumerate()
+        .map(|(i, middle)| {
+            let middle = middle?;
+            let window = &closes[i + 1 - period..=i];
+            let variance = window.iter().map(|c| (c - middle).powi(2)).sum::<f64>() / period as f64;
+            Some(Bands {
+                lower: middle - k * variance.sqrt(),
+                middle,
+                upper: middle + k * variance.sqrt(),
+            })
+        })
+        .collect();
+    points(&bars, values)
+}
+
+// average true range, the true range also covers gaps from the previous close
+pub fn atr(list: &CandleList, interval: &Interval, period: usize) -> Vec<Point<f64>> {
+    let bars = bars(list, interval);
+    let ranges: Vec<f64> = bars
+        .iter()
+        .enumerate()
+        .map(|(i, b)| match i {
+            0 => b.high - b.low,
+            _ => {
+                let prev = bars[i - 1].close;
+                (b.high - b.low)
+                    .max((b.high - prev).abs())
+                    .max((b.low - prev).abs())
+            }
+        })
+        .collect();
+    points(&bars, wilder_of(&ranges, period))
+}
@@ -0,0 +1,138 @@
+use super::candles::{CandleList, OHLC};
+use super::price::Price;
+use std::fmt;
+
+// what to do with a slot where only one leg of the pair has a price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CrossGap {
+    // leave the cross candle empty and let the gap fill policy handle it
+    Skip,
+    // price the missing leg at its last close and mark the cross candle synthetic
+    HoldLast,
+}
+impl fmt::Display for CrossGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CrossGap::Skip => write!(f, "skip"),
+            CrossGap::HoldLast => write!(f, "hold last price"),
+        }
+    }
+}
+
+// how many slots each leg had real ticks for
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrossReport {
+    pub matched: usize,
+    pub base_missing: usize,
+    pub quote_missing: usize,
+    pub both_missing: usize,
+}
+impl fmt::Display for CrossReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} matched slots, {} missing base, {} missing quote, {} missing both",
+            self.matched, self.base_missing, self.quote_missing, self.both_missing
+        )
+    }
+}
+
+//
+// base/quote candles from two lists at the same resolution, e.g. SOL/USD and BTC/USD
+// for SOL/BTC. slots are matched by open time over the span of both lists and a leg
+// only counts as present when it has a real (not gap filled) candle in that slot.
+// the cross list uses the base lists options
+//
+pub fn cross_candles(
+    base: &CandleList,
+    quote: &CandleList,
+    gaps: CrossGap,
+) -> Result<(CandleList, CrossReport), &'static str> {
+    if base.resolution() != quote.resolution() {
+        return Err("both legs need the same candle resolution");
+    }
+    let resolution = base.resolution();
+    let (start, end) = if base.is_empty() {
+        (quote.start(), quote.end())
+    } else if quote.is_empty() {
+        (base.start(), base.end())
+    } else {
+        if (base.start() - quote.start()) % resolution != 0 {
+            return Err("candle slots of the two legs are not aligned");
+        }
+        (base.start().min(quote.start()), base.end().max(quote.end()))
+    };
+
+    let real = |c: Option<&OHLC>| c.filter(|c| c.is_valid() && !c.synthetic).copied();
+    let mut report = CrossReport::default();
+    let mut last_base: Option<Price> = None;
+    let mut last_quote: Option<Price> = None;
+    let mut candles = Vec::new();
+    let mut time = start;
+    while time < end {
+        let b = real(base.candle_at(time));
+        let q = real(quote.candle_at(time));
+        let candle = match (b, q) {
+            (Some(b), Some(q)) => {
+                report.matched += 1;
+                ratio_candle(&b, &q)
+            }
+            (Some(b), None) => {
+                report.quote_missing += 1;
+                match (gaps, last_quote) {
+                    (CrossGap::HoldLast, Some(p)) => ratio_candle(&b, &OHLC::synthetic(p, p)),
+                    _ => OHLC::new(),
+                }
+            }
+            (None, Some(q)) => {
+                report.base_missing += 1;
+                match (gaps, last_base) {
+                    (CrossGap::HoldLast, Some(p)) => ratio_candle(&OHLC::synthetic(p, p), &q),
+                    _ => OHLC::new(),
+                }
+            }
+            (None, None) => {
+                report.both_missing += 1;
+                OHLC::new()
+            }
+        };
+        if let Some(b) = b {
+            last_base = b.close;
+        }
+        if let Some(q) = q {
+            last_quote = q.close;
+        }
+        candles.push(candle);
+        time += resolution;
+    }
+    let list = CandleList::new(start, resolution, candles, base.options());
+    Ok((list, report))
+}
+
+//
+// open and close divide straight across, high and low are the widest the ratio could
+// have been inside the slot since the legs highs and lows need not line up
+//
+fn ratio_candle(base: &OHLC, quote: &OHLC) -> OHLC {
+    if !base.is_valid() || !quote.is_valid() || !quote.low.unwrap().is_positive() {
+        return OHLC::new();
+    }
+    let ratio =
+        |b: Option<Price>, q: Option<Price>| b.unwrap().checked_div(&q.unwrap(), RATIO_EXPO);
+    let latest = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    };
+    OHLC {
+        open_time: latest(base.open_time, quote.open_time),
+        open: ratio(base.open, quote.open),
+        high: ratio(base.high, quote.low),
+        low: ratio(base.low, quote.high),
+        close: ratio(base.close, quote.close),
+        close_time: latest(base.close_time, quote.close_time),
+        synthetic: base.synthetic || quote.synthetic,
+        ..OHLC::new()
+    }
+}
+// cross prices can be far below either leg, e.g. SOL/BTC, so keep plenty of digits
+const RATIO_EXPO: i32 = -12;
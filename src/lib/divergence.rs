@@ -0,0 +1,148 @@
+use super::candles::{CandleList, Interval, OHLC};
+use super::price::Price;
+use super::utc_to_datetime;
+use std::collections::HashMap;
+use std::fmt;
+
+// serum against pyth for one bucket where both had real ticks
+#[derive(Debug, Clone, Copy)]
+pub struct Divergence {
+    // unix timestamp the bucket opened at
+    pub time: i64,
+    pub pyth: Price,
+    pub serum: Price,
+    // serum close - pyth close
+    pub basis: Price,
+    // basis as a percent of the pyth close
+    pub basis_pct: f64,
+    // widest confidence interval pyth published in the bucket
+    pub conf: f64,
+    // serum traded further than conf from every pyth price in the bucket
+    pub outside_band: bool,
+}
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}, Pyth: {:.4} ±{:.4}, Serum: {:.4}, Basis: {:.4} ({:+.3}%)",
+            utc_to_datetime(self.time),
+            self.pyth,
+            self.conf,
+            self.serum,
+            self.basis,
+            self.basis_pct
+        )?;
+        if self.outside_band {
+            write!(f, ", outside conf")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DivergenceReport {
+    pub matched: usize,
+    pub pyth_missing: usize,
+    pub serum_missing: usize,
+    // matched buckets where serum traded outside the pyth confidence band
+    pub outside_band: usize,
+    // basis percent furthest from zero, keeping its sign
+    pub largest_basis_pct: Option<f64>,
+}
+impl fmt::Display for DivergenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} matched buckets, {} outside conf, {} missing pyth, {} missing serum",
+            self.matched, self.outside_band, self.pyth_missing, self.serum_missing
+        )?;
+        if let Some(pct) = self.largest_basis_pct {
+            write!(f, ", largest basis {:+.3}%", pct)?;
+        }
+        Ok(())
+    }
+}
+
+//
+// compares the interval candles of the same asset on pyth and serum, e.g. SOL/USD and
+// SOLUSDC. buckets are matched by open time, so build both lists with the same end and
+// options. serum buckets outside the span of the pyth list are left out, bonfida can
+// return trades from long before the pyth window. the confidence band runs from the
+// pyth low - conf to the pyth high + conf using the widest conf in the bucket, and
+// serum is outside it when its high or low is outside that band
+//
+pub fn divergence(
+    pyth: &CandleList,
+    serum: &CandleList,
+    interval: &Interval,
+) -> (Vec<Divergence>, DivergenceReport) {
+    let real = |c: &OHLC| c.is_valid() && !c.synthetic;
+    let pyth_candles = pyth.get_timed_candles(interval);
+    let (first, last) = match (pyth_candles.first(), pyth_candles.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return (Vec::new(), DivergenceReport::default()),
+    };
+    let serum_candles: HashMap<i64, OHLC> = serum
+        .get_timed_candles(interval)
+        .into_iter()
+        .filter(|(time, c)| *time >= first && *time <= last && real(c))
+        .collect();
+
+    let mut report = DivergenceReport::default();
+    let mut points = Vec::new();
+    let mut seen = 0;
+    for (time, p) in pyth_candles.into_iter() {
+        if !real(&p) {
+            continue;
+        }
+        let s = match serum_candles.get(&time) {
+            Some(s) => s,
+            None => {
+                report.serum_missing += 1;
+                continue;
+            }
+        };
+        seen += 1;
+        if let Some(point) = compare(time, &p, s) {
+            report.matched += 1;
+            if point.outside_band {
+                report.outside_band += 1;
+            }
+            if report
+                .largest_basis_pct
+                .is_none_or(|pct| point.basis_pct.abs() > pct.abs())
+            {
+                report.largest_basis_pct = Some(point.basis_pct);
+            }
+            points.push(point);
+        }
+    }
+    report.pyth_missing = serum_candles.len() - seen;
+    (points, report)
+}
+
+fn compare(time: i64, pyth: &OHLC, serum: &OHLC) -> Option<Divergence> {
+    let (pyth_close, serum_close) = (pyth.close?, serum.close?);
+    if !pyth_close.is_positive() {
+        return None;
+    }
+    let basis = serum_close.checked_sub(&pyth_close)?;
+    let conf = pyth.conf_high.unwrap_or(0.0);
+    let lower = pyth.low?.to_f64() - conf;
+    let upper = pyth.high?.to_f64() + conf;
+    Some(Divergence {
+        time,
+        pyth: pyth_close,
+        serum: serum_close,
+        basis,
+        basis_pct: basis.to_f64() / pyth_close.to_f64() * 100.0,
+        conf,
+        outside_band: serum.low?.to_f64() < lower || serum.high?.to_f64() > upper,
+    })
+}
+
+pub fn print_divergence(points: &[Divergence]) {
+    for p in points.iter() {
+        println!("{}", p);
+    }
+}
@@ -0,0 +1,196 @@
+use super::source::Tick;
+use std::collections::VecDeque;
+use std::fmt;
+
+// checks run on each tick before it is put into a candle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TickFilter {
+    // drop ticks more than n confidence intervals away from the last kept price
+    ConfDeviation(f64),
+    // drop ticks more than threshold median absolute deviations from the median
+    // of the last window prices
+    MedianDeviation { window: usize, threshold: f64 },
+    // drop ticks that move more than this percent from the last kept price
+    MaxJump(f64),
+}
+impl fmt::Display for TickFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TickFilter::ConfDeviation(n) => write!(f, "{} x conf", n),
+            TickFilter::MedianDeviation { window, threshold } => {
+                write!(f, "{} x MAD over {} ticks", threshold, window)
+            }
+            TickFilter::MaxJump(pct) => write!(f, "{}% jump", pct),
+        }
+    }
+}
+
+// how many ticks each filter dropped
+#[derive(Debug, Clone, Default)]
+pub struct FilterReport {
+    pub kept: usize,
+    pub dropped: Vec<(TickFilter, usize)>,
+    // runs of rejected ticks taken as a real level shift
+    pub reanchored: usize,
+}
+impl FilterReport {
+    pub fn total_dropped(&self) -> usize {
+        self.dropped.iter().map(|(_, n)| n).sum()
+    }
+}
+impl fmt::Display for FilterReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "kept {} ticks", self.kept)?;
+        for (filter, n) in self.dropped.iter() {
+            write!(f, ", {} dropped by {}", n, filter)?;
+        }
+        if self.reanchored > 0 {
+            write!(f, ", re-anchored {} times", self.reanchored)?;
+        }
+        Ok(())
+    }
+}
+
+//
+// runs the filters over ticks in time order. a tick is dropped by the first filter
+// that rejects it. the median window takes every tick so it follows the market, the
+// last price only moves on kept ticks. after REANCHOR_AFTER ticks in a row are
+// rejected the price is taken to have really moved and that tick is kept as the new
+// reference, so a level shift doesnt drop the rest of the window. the run only counts
+// while it holds a level: every tick on the same side of the last price and within the
+// jump and conf limits of the tick before it, so an erratic spike keeps being dropped.
+// the first tick is always kept
+//
+pub struct TickFilters {
+    filters: Vec<TickFilter>,
+    recent: VecDeque<f64>,
+    window: usize,
+    last: Option<f64>,
+    // length, side and latest tick of the current run of rejected ticks
+    rejected: usize,
+    run_above: bool,
+    run_last: Option<Tick>,
+    report: FilterReport,
+}
+const REANCHOR_AFTER: usize = 5;
+impl TickFilters {
+    pub fn new(filters: &[TickFilter]) -> Self {
+        let window = filters
+            .iter()
+            .map(|f| match f {
+                TickFilter::MedianDeviation { window, .. } => *window,
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0);
+        Self {
+            filters: filters.to_vec(),
+            recent: VecDeque::with_capacity(window),
+            window,
+            last: None,
+            rejected: 0,
+            run_above: false,
+            run_last: None,
+            report: FilterReport {
+                kept: 0,
+                dropped: filters.iter().map(|f| (*f, 0)).collect(),
+                reanchored: 0,
+            },
+        }
+    }
+    pub fn accept(&mut self, tick: &Tick) -> bool {
+        let rejected_by = match self.last {
+            Some(last) => self
+                .filters
+                .iter()
+                .position(|filter| self.rejects(filter, tick, last)),
+            None => None,
+        };
+        let price = tick.price.to_f64();
+        if self.window > 0 {
+            if self.recent.len() == self.window {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(price);
+        }
+        if let Some(i) = rejected_by {
+            let above = price > self.last.unwrap();
+            let holds_level = match self.run_last {
+                Some(prev) => above == self.run_above && !self.jumps(tick, &prev),
+                None => false,
+            };
+            self.rejected = if holds_level { self.rejected + 1 } else { 1 };
+            self.run_above = above;
+            self.run_last = Some(*tick);
+            if self.rejected < REANCHOR_AFTER {
+                self.report.dropped[i].1 += 1;
+                return false;
+            }
+            // only the run at the new level stays in the median window
+            let stale = self.recent.len().saturating_sub(REANCHOR_AFTER);
+            self.recent.drain(..stale);
+            self.report.reanchored += 1;
+        }
+        self.rejected = 0;
+        self.run_last = None;
+        self.last = Some(price);
+        self.report.kept += 1;
+        true
+    }
+    pub fn report(&self) -> &FilterReport {
+        &self.report
+    }
+    // whether tick moved further from prev than the jump or conf filters allow
+    fn jumps(&self, tick: &Tick, prev: &Tick) -> bool {
+        let prev = prev.price.to_f64();
+        self.filters.iter().any(|filter| match filter {
+            TickFilter::MedianDeviation { .. } => false,
+            _ => self.rejects(filter, tick, prev),
+        })
+    }
+    fn rejects(&self, filter: &TickFilter, tick: &Tick, last: f64) -> bool {
+        let price = tick.price.to_f64();
+        match filter {
+            TickFilter::ConfDeviation(n) => match tick.conf {
+                Some(conf) => (price - last).abs() > n * conf,
+                None => false,
+            },
+            TickFilter::MaxJump(pct) => (price / last - 1.0).abs() * 100.0 > *pct,
+            TickFilter::MedianDeviation { window, threshold } => {
+                // needs a few prices to say anything about spread
+                let recent: Vec<f64> = self.recent.iter().rev().take(*window).cloned().collect();
+                if recent.len() < 3 {
+                    return false;
+                }
+                let m = median(&recent);
+                let deviations: Vec<f64> = recent.iter().map(|p| (p - m).abs()).collect();
+                // 1.4826 scales the MAD to a standard deviation for normal data
+                let mad = 1.4826 * median(&deviations);
+                if mad == 0.0 {
+                    return false;
+                }
+                (price - m).abs() > threshold * mad
+            }
+        }
+    }
+}
+
+// sorts ticks by time and returns the ones every filter kept
+pub fn filter_ticks(ticks: &[Tick], filters: &[TickFilter]) -> (Vec<Tick>, FilterReport) {
+    let mut ticks = ticks.to_vec();
+    ticks.sort_by(|a, b| (a.time, a.slot).partial_cmp(&(b.time, b.slot)).unwrap());
+    let mut checks = TickFilters::new(filters);
+    let kept: Vec<Tick> = ticks.into_iter().filter(|t| checks.accept(t)).collect();
+    (kept, checks.report().clone())
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
@@ -1,10 +1,16 @@
 pub mod candles;
+pub mod cross;
+pub mod divergence;
+pub mod filter;
+pub mod price;
 pub mod pyth;
 pub mod serum;
+pub mod source;
+pub mod stream;
 use crate::pyth::{PythAccount, PythData, PythProduct};
 use chrono::prelude::DateTime;
-use chrono::Duration;
 use chrono::Utc;
+use chrono::{NaiveDateTime, NaiveTime};
 use progress_bar::color::{Color, Style};
 use progress_bar::progress_bar::ProgressBar;
 use pyth::PriceAccountResult;
@@ -59,9 +65,13 @@ impl PythClient {
                 if !prod_acct.is_valid() {
                     continue;
                 }
-                // loop through reference attributes and find symbol
-                let prod_attr_sym = match prod_acct.get_symbol() {
-                    Some(s) => s,
+                // decode reference attributes and find symbol
+                let attributes = match prod_acct.decode_attributes() {
+                    Some(a) => a,
+                    None => continue,
+                };
+                let prod_attr_sym = match attributes.get("symbol") {
+                    Some(s) => s.clone(),
                     None => continue,
                 };
                 // add to vector if price accounts are valid
@@ -70,6 +80,7 @@ impl PythClient {
                         name: prod_attr_sym,
                         key: prod_pkey,
                         price_accounts: prod_acct.px_acc.val,
+                        attributes,
                     });
                 }
                 // go to next account if valid
@@ -86,6 +97,14 @@ impl PythClient {
         }
         return Ok(products);
     }
+    // products whose reference attributes pass filter
+    pub fn get_filtered_products(
+        &self,
+        filter: &pyth::ProductFilter,
+    ) -> Result<Vec<pyth::ProductResult>, &'static str> {
+        let products = self.get_product_accounts()?;
+        Ok(pyth::filter_products(&products, filter))
+    }
 
     pub fn get_price_account_data(
         &self,
@@ -115,18 +134,39 @@ impl PythClient {
             continue;
         }
     }
+    // price history for both legs of a cross pair over the same window
+    pub fn get_cross_data(
+        &self,
+        base: [u8; 32],
+        quote: [u8; 32],
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+    ) -> Result<(PythData, PythData), &'static str> {
+        let base = self.get_price_account_data(base)?;
+        let quote = self.get_price_account_data(quote)?;
+        let base = self.get_historical_data(&base, start_time, end_time)?;
+        let quote = self.get_historical_data(&quote, start_time, end_time)?;
+        Ok((base, quote))
+    }
+    //
+    // price updates with start_time <= block time < end_time. signatures come newest
+    // first so pages are skipped until they reach end_time, then transactions are
+    // collected until they pass start_time
+    //
     pub fn get_historical_data(
         &self,
-        px_acct: Pubkey,
+        px_acct: &PriceAccountResult,
         start_time: DateTime<Utc>,
-        duration: Duration,
+        end_time: DateTime<Utc>,
     ) -> Result<PythData, &'static str> {
+        if end_time <= start_time {
+            return Err("window end must be after its start");
+        }
         // we can request 1000 sig per req
         let mut last_sig: Option<Signature> = None;
         let mut signature_list: Vec<pyth::PriceResult> = Vec::new();
 
-        let end_time = start_time - duration; // for debugging the interval is set low
-        let duration_us = duration.num_microseconds().unwrap();
+        let duration_us = (end_time - start_time).num_microseconds().unwrap();
 
         let mut progress_bar = ProgressBar::new(100);
         progress_bar.set_action(" Progress", Color::Blue, Style::Bold);
@@ -141,7 +181,7 @@ impl PythClient {
 
             let px_sigs = self
                 .client
-                .get_signatures_for_address_with_config(&px_acct, rqt_config);
+                .get_signatures_for_address_with_config(&px_acct.key, rqt_config);
             let price_account_signatures = match px_sigs {
                 Ok(result) => result,
                 Err(error) => {
@@ -149,7 +189,14 @@ impl PythClient {
                     continue;
                 }
             };
+            // ran out of history before reaching the start
+            if price_account_signatures.is_empty() {
+                progress_bar.finalize();
+                break 'process_px_acct;
+            }
             for sig in price_account_signatures {
+                let s = Signature::from_str(&sig.signature).unwrap();
+                last_sig = Some(s);
                 // check for signature error
                 if let Some(_) = sig.err {
                     continue;
@@ -157,14 +204,15 @@ impl PythClient {
                 // check time duration
                 let time = sig.block_time.unwrap() as i64;
                 let block_time = utc_to_datetime(time);
-                if block_time < end_time {
+                if block_time >= end_time {
+                    continue;
+                }
+                if block_time < start_time {
                     progress_bar.set_progression(100);
                     progress_bar.finalize();
                     break 'process_px_acct;
                 }
                 // request transaction from signature
-                let s = Signature::from_str(&sig.signature).unwrap();
-                last_sig = Some(s);
                 let txn = match self
                     .client
                     .get_transaction(&s, UiTransactionEncoding::Base64)
@@ -189,7 +237,7 @@ impl PythClient {
                 signature_list.push(data.to_price_result(time));
 
                 // update progress bar
-                let progress_microseconds = (start_time - block_time).num_microseconds().unwrap();
+                let progress_microseconds = (end_time - block_time).num_microseconds().unwrap();
                 let time_progress = (100.0 * progress_microseconds as f32) / (duration_us as f32);
                 progress_bar.set_progression(time_progress as usize);
             }
@@ -200,6 +248,7 @@ impl PythClient {
         println!(""); // progress bar gets in the way
         Ok(PythData {
             data: signature_list,
+            expo: px_acct.expo,
         })
     }
 }
@@ -250,6 +299,37 @@ impl SerumClient {
     }
 }
 
+//
+// parses a utc window like "2021-07-02 12:00 to 18:00 UTC". the end can be a full
+// date and time or just a time on the start date
+//
+pub fn parse_window(s: &str) -> Result<(DateTime<Utc>, DateTime<Utc>), &'static str> {
+    let s = s.trim();
+    let s = s.strip_suffix("UTC").unwrap_or(s).trim();
+    let (start, end) = match s.split_once(" to ") {
+        Some(i) => i,
+        None => return Err("window should look like 2021-07-02 12:00 to 18:00 UTC"),
+    };
+    let start = match NaiveDateTime::parse_from_str(start.trim(), "%Y-%m-%d %H:%M") {
+        Ok(i) => i,
+        Err(_) => return Err("window start should look like 2021-07-02 12:00"),
+    };
+    let end = match NaiveDateTime::parse_from_str(end.trim(), "%Y-%m-%d %H:%M") {
+        Ok(i) => i,
+        Err(_) => match NaiveTime::parse_from_str(end.trim(), "%H:%M") {
+            Ok(t) => start.date().and_time(t),
+            Err(_) => return Err("window end should look like 18:00 or 2021-07-02 18:00"),
+        },
+    };
+    if end <= start {
+        return Err("window end must be after its start");
+    }
+    Ok((
+        DateTime::<Utc>::from_utc(start, Utc),
+        DateTime::<Utc>::from_utc(end, Utc),
+    ))
+}
+
 pub fn utc_to_datetime(t: i64) -> DateTime<Utc> {
     let t = UNIX_EPOCH + StdDuration::from_secs(t as u64);
     let t = DateTime::<Utc>::from(t);
@@ -0,0 +1,227 @@
+use core::cmp::Ordering;
+use core::fmt;
+use core::ops::{Add, Sub};
+use serde::Deserialize;
+
+//
+// fixed point price worth mantissa * 10^expo, the same layout pyth stores prices in.
+// sums and comparisons are exact, division rounds half away from zero at a chosen
+// exponent so the same inputs always settle to the same value. convert to f64 only
+// for display and statistics. the checked methods return none when a mantissa would
+// overflow, the operators panic like integer overflow does
+//
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Price {
+    pub mantissa: i128,
+    pub expo: i32,
+}
+impl Price {
+    pub fn new(mantissa: i128, expo: i32) -> Self {
+        Self { mantissa, expo }
+    }
+    // exact decimal value of the shortest string that round trips the float,
+    // so 0.1 becomes 1 * 10^-1 rather than the nearest binary fraction
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        let s = format!("{}", value);
+        let (whole, frac) = match s.split_once('.') {
+            Some((whole, frac)) => (whole, frac),
+            None => (s.as_str(), ""),
+        };
+        let mantissa: i128 = format!("{}{}", whole, frac).parse().ok()?;
+        Some(Self::new(mantissa, -(frac.len() as i32)))
+    }
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 * 10f64.powi(self.expo)
+    }
+    pub fn is_positive(&self) -> bool {
+        self.mantissa > 0
+    }
+    // same value at another exponent, rounding if digits are dropped
+    pub fn rescale(&self, expo: i32) -> Option<Self> {
+        let mantissa = match expo.cmp(&self.expo) {
+            Ordering::Equal => self.mantissa,
+            Ordering::Less => self.mantissa.checked_mul(pow10(self.expo - expo)?)?,
+            // more digits dropped than an i128 holds rounds to zero
+            Ordering::Greater => match pow10(expo - self.expo) {
+                Some(p) => div_round(self.mantissa, p),
+                None => 0,
+            },
+        };
+        Some(Self::new(mantissa, expo))
+    }
+    pub fn checked_add(&self, other: &Price) -> Option<Self> {
+        let (a, b, expo) = align(self, other)?;
+        Some(Self::new(a.checked_add(b)?, expo))
+    }
+    pub fn checked_sub(&self, other: &Price) -> Option<Self> {
+        let (a, b, expo) = align(self, other)?;
+        Some(Self::new(a.checked_sub(b)?, expo))
+    }
+    // divides by a whole number, rounding at the current exponent
+    pub fn div_int(&self, n: i128) -> Self {
+        Self::new(div_round(self.mantissa, n), self.expo)
+    }
+    // self / other rounded at expo, none when other is zero or it overflows
+    pub fn checked_div(&self, other: &Price, expo: i32) -> Option<Self> {
+        if other.mantissa == 0 {
+            return None;
+        }
+        // value = (a * 10^ea) / (b * 10^eb) = m * 10^expo
+        let shift = self.expo - other.expo - expo;
+        let (numer, denom) = if shift >= 0 {
+            (self.mantissa.checked_mul(pow10(shift)?)?, other.mantissa)
+        } else {
+            (self.mantissa, other.mantissa.checked_mul(pow10(-shift)?)?)
+        };
+        Some(Self::new(div_round(numer, denom), expo))
+    }
+    // mean at the smallest exponent of the prices, none if the sum overflows
+    pub fn mean(prices: &[Price]) -> Option<Self> {
+        if prices.is_empty() {
+            return None;
+        }
+        let sum = prices[1..]
+            .iter()
+            .try_fold(prices[0], |sum, p| sum.checked_add(p))?;
+        Some(sum.div_int(prices.len() as i128))
+    }
+}
+// lines two prices up on the smaller exponent
+fn align(a: &Price, b: &Price) -> Option<(i128, i128, i32)> {
+    let expo = a.expo.min(b.expo);
+    Some((a.rescale(expo)?.mantissa, b.rescale(expo)?.mantissa, expo))
+}
+fn pow10(n: i32) -> Option<i128> {
+    if n < 0 {
+        return None;
+    }
+    10i128.checked_pow(n as u32)
+}
+fn div_round(numer: i128, denom: i128) -> i128 {
+    let q = numer / denom;
+    let r = numer % denom;
+    if 2 * r.abs() >= denom.abs() {
+        if (numer < 0) == (denom < 0) {
+            q + 1
+        } else {
+            q - 1
+        }
+    } else {
+        q
+    }
+}
+impl Add for Price {
+    type Output = Price;
+
+    fn add(self, other: Price) -> Price {
+        self.checked_add(&other).expect("price overflow")
+    }
+}
+impl Sub for Price {
+    type Output = Price;
+
+    fn sub(self, other: Price) -> Price {
+        self.checked_sub(&other).expect("price overflow")
+    }
+}
+impl PartialEq for Price {
+    fn eq(&self, other: &Price) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl Eq for Price {}
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Price) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Price {
+    fn cmp(&self, other: &Price) -> Ordering {
+        match align(self, other) {
+            Some((a, b, _)) => a.cmp(&b),
+            // the one that cant be scaled down to the other is larger in size,
+            // so its sign decides
+            None if self.expo > other.expo => self.mantissa.cmp(&0),
+            None => 0.cmp(&other.mantissa),
+        }
+    }
+}
+// prints every digit, or rounds to the precision when one is given e.g. {:.2}
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let p = match f.precision() {
+            Some(digits) => self.rescale(-(digits as i32)).unwrap_or(*self),
+            None => *self,
+        };
+        if p.expo >= 0 {
+            let zeros = if p.mantissa == 0 { 0 } else { p.expo as usize };
+            return write!(f, "{}{}", p.mantissa, "0".repeat(zeros));
+        }
+        let digits = format!(
+            "{:0>width$}",
+            p.mantissa.abs(),
+            width = -p.expo as usize + 1
+        );
+        let (whole, frac) = digits.split_at(digits.len() - (-p.expo) as usize);
+        let sign = if p.mantissa < 0 { "-" } else { "" };
+        write!(f, "{}{}.{}", sign, whole, frac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn div_rounds_half_away_from_zero() {
+        assert_eq!(Price::new(15, -1).rescale(0).unwrap().mantissa, 2);
+        assert_eq!(Price::new(-15, -1).rescale(0).unwrap().mantissa, -2);
+        assert_eq!(Price::new(14, -1).rescale(0).unwrap().mantissa, 1);
+        assert_eq!(Price::new(-14, -1).rescale(0).unwrap().mantissa, -1);
+        assert_eq!(Price::new(5, 0).div_int(2).mantissa, 3);
+        assert_eq!(Price::new(-5, 0).div_int(2).mantissa, -3);
+        assert_eq!(Price::new(5, 0).div_int(-2).mantissa, -3);
+        assert_eq!(Price::new(7, 0).div_int(3).mantissa, 2);
+    }
+
+    #[test]
+    fn rescale_past_i128_rounds_to_zero() {
+        let p = Price::new(i128::MAX, -50).rescale(0).unwrap();
+        assert_eq!((p.mantissa, p.expo), (0, 0));
+        assert_eq!(Price::new(1, 0).rescale(-39), None);
+    }
+
+    #[test]
+    fn ord_without_common_exponent() {
+        let big = Price::new(5, 30);
+        let small = Price::new(i128::MAX, -10);
+        assert_eq!(big.cmp(&small), Ordering::Greater);
+        assert_eq!(small.cmp(&big), Ordering::Less);
+        assert_eq!(Price::new(-5, 30).cmp(&small), Ordering::Less);
+        assert_eq!(small.cmp(&Price::new(-5, 30)), Ordering::Greater);
+        assert_eq!(Price::new(10, -1), Price::new(1, 0));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(format!("{}", Price::new(12345, -4)), "1.2345");
+        assert_eq!(format!("{:.2}", Price::new(12345, -4)), "1.23");
+        assert_eq!(format!("{:.2}", Price::new(12355, -4)), "1.24");
+        assert_eq!(format!("{}", Price::new(-5, -2)), "-0.05");
+        assert_eq!(format!("{:.1}", Price::new(-15, -2)), "-0.2");
+        assert_eq!(format!("{}", Price::new(12, 3)), "12000");
+        assert_eq!(format!("{}", Price::new(0, 3)), "0");
+    }
+
+    #[test]
+    fn from_f64_is_exact() {
+        let p = Price::from_f64(0.1).unwrap();
+        assert_eq!((p.mantissa, p.expo), (1, -1));
+        let p = Price::from_f64(-42.5).unwrap();
+        assert_eq!((p.mantissa, p.expo), (-425, -1));
+        assert_eq!(Price::from_f64(f64::NAN), None);
+    }
+}
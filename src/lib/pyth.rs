@@ -1,5 +1,6 @@
-use super::candles::{CandleList, OHLC};
-use arr_macro::arr;
+use super::candles::{CandleList, CandleOptions};
+use super::price;
+use super::source::{build_slot_candles, PriceSource, Tick};
 use chrono::prelude::DateTime;
 use chrono::Utc;
 use pyth_client::{
@@ -37,11 +38,19 @@ pub struct PriceResult {
     pub block_time: i64,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct ProductResult {
     pub name: String,
     pub key: Pubkey,
     pub price_accounts: [u8; 32],
+    // every reference attribute on the product account, e.g. asset_type, country,
+    // quote_currency, tenor, description and generic_symbol
+    pub attributes: HashMap<String, String>,
+}
+impl ProductResult {
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(|v| v.as_str())
+    }
 }
 impl fmt::Display for ProductResult {
     // This trait requires `fmt` with this exact signature.
@@ -55,6 +64,12 @@ pub struct PriceAccountResult {
     pub expo: i32,
     pub twap: i64,
 }
+impl PriceAccountResult {
+    // twap stored on the price account
+    pub fn onchain_twap(&self) -> price::Price {
+        price::Price::new(self.twap as i128, self.expo)
+    }
+}
 
 pub trait PythAccount {
     fn is_valid(&self) -> bool;
@@ -156,7 +171,7 @@ where
     return val;
 }
 
-pub fn find_product(products: &Vec<ProductResult>, s: String) -> Option<[u8; 32]> {
+pub fn find_product(products: &[ProductResult], s: String) -> Option<[u8; 32]> {
     for p in products.iter() {
         if p.name == s {
             return Some(p.price_accounts);
@@ -168,98 +183,149 @@ pub fn find_product(products: &Vec<ProductResult>, s: String) -> Option<[u8; 32]
     );
     None
 }
+//
+// narrows the product list by reference attributes, e.g. only Crypto quoted in USD.
+// unset fields match anything and values are compared ignoring case
+//
+#[derive(Debug, Clone, Default)]
+pub struct ProductFilter {
+    pub asset_type: Option<String>,
+    pub quote_currency: Option<String>,
+    pub country: Option<String>,
+    pub tenor: Option<String>,
+}
+impl ProductFilter {
+    pub fn matches(&self, product: &ProductResult) -> bool {
+        let checks = [
+            ("asset_type", &self.asset_type),
+            ("quote_currency", &self.quote_currency),
+            ("country", &self.country),
+            ("tenor", &self.tenor),
+        ];
+        checks.iter().all(|(key, want)| match want {
+            Some(want) => product
+                .attribute(key)
+                .is_some_and(|v| v.eq_ignore_ascii_case(want)),
+            None => true,
+        })
+    }
+}
+
+pub fn filter_products(products: &[ProductResult], filter: &ProductFilter) -> Vec<ProductResult> {
+    products
+        .iter()
+        .filter(|p| filter.matches(p))
+        .cloned()
+        .collect()
+}
+
+// sorted distinct values of an attribute across the products, for building filters
+pub fn attribute_values(products: &[ProductResult], key: &str) -> Vec<String> {
+    let mut values: Vec<String> = products
+        .iter()
+        .filter_map(|p| p.attribute(key))
+        .map(String::from)
+        .collect();
+    values.sort();
+    values.dedup();
+    values
+}
+
 pub struct PythData {
     pub data: Vec<PriceResult>,
+    pub expo: i32,
 }
 impl PythData {
-    pub fn get_pyth_candles(&self, start: &DateTime<Utc>, expo: i32) -> CandleList {
-        let mut candle_data: [Vec<PriceResult>; 1440] = arr![Vec::new(); 1440];
-        let interval = chrono::Duration::seconds(60).num_seconds();
-        let start = start.timestamp();
-        for tx in self.data.iter() {
-            //
-            // builds index and stores pyth transactions in vectors representing 1 min of data
-            // reverses order of candle so earliest price is 0 index
-            //
-            let i = (start - tx.block_time) / interval;
-            let i = candle_data.len() - 1 - i as usize;
-            candle_data[i].push(tx.clone());
-        }
+    pub fn get_pyth_candles(
+        &self,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        options: &CandleOptions,
+    ) -> CandleList {
+        self.candles(start, end, options)
+    }
+    // candles bucketed by pub_slot instead of block time
+    pub fn get_slot_candles(&self, slots: u64, options: &CandleOptions) -> CandleList {
+        build_slot_candles(&self.ticks(), slots, options)
+    }
+    //
+    // rebuilds the oracle's twap with its update rule: each price is weighted by 1/conf
+    // and the running sums are scaled by 1 + slots * EMA_DECAY between updates, a
+    // linear step towards a 5921 slot half life. a gap longer than EMA_MAX_GAP slots
+    // restarts it. the ema starts cold at the first fetched update, since the stored
+    // twap already includes the fetched window, so it only settles on the on-chain
+    // value after about EMA_WARM_UP slots of history. the oracle also updates it once
+    // per slot from the aggregate price where this runs over every publisher update,
+    // so expect it to be close but not exact
+    //
+    pub fn ema_twap(&self) -> Option<EmaTwap> {
+        let mut data = self.data.clone();
+        data.sort_by_key(|d| (d.pub_slot, d.block_time));
+        let scale_factor = 10f64.powi(self.expo);
 
-        let mut candles = [OHLC::new(); 1440];
-        for (i, c) in candle_data.iter().enumerate() {
-            let mut candle = make_pyth_candle(c, expo);
-            if !candle.is_valid() {
-                if i != 0 && candles[i - 1].is_valid() {
-                    //
-                    //  if no data set fields to prev candles close price
-                    //
-                    candle = OHLC {
-                        open_time: None,
-                        open: candles[i - 1].close,
-                        high: candles[i - 1].close,
-                        low: candles[i - 1].close,
-                        close: candles[i - 1].close,
-                        close_time: None,
-                    }
+        let mut numer = 0.0;
+        let mut denom = 0.0;
+        let mut first_slot = 0;
+        let mut last_slot: Option<u64> = None;
+        for d in data.iter() {
+            let price = d.price as f64 * scale_factor;
+            let weight = match d.conf {
+                0 => 1.0,
+                conf => 1.0 / (conf as f64 * scale_factor),
+            };
+            match last_slot {
+                Some(slot) if d.pub_slot - slot <= EMA_MAX_GAP => {
+                    let decay = 1.0 + (d.pub_slot - slot) as f64 * EMA_DECAY;
+                    numer = numer * decay + price * weight;
+                    denom = denom * decay + weight;
+                }
+                _ => {
+                    numer = price * weight;
+                    denom = weight;
+                    first_slot = d.pub_slot;
                 }
             }
-            //
-            // next candles open price should equal prev candles close price
-            //
-            if i != 0 && candles[i - 1].close != None {
-                candle.open = candles[i - 1].close;
-            }
-            candles[i] = candle
+            last_slot = Some(d.pub_slot);
+        }
+        if denom == 0.0 {
+            return None;
         }
-        return CandleList::new(candles);
+        Some(EmaTwap {
+            value: numer / denom,
+            slots: last_slot? - first_slot,
+        })
     }
 }
-fn make_pyth_candle(transactions: &Vec<PriceResult>, expo: i32) -> OHLC {
-    if transactions.len() == 0 {
-        return OHLC::new();
-    }
-
-    let mut open_time: Option<i64> = None;
-    let mut open: Option<i64> = None;
-    let mut high: Option<i64> = None;
-    let mut low: Option<i64> = None;
-    let mut close_time: Option<i64> = None;
-    let mut close: Option<i64> = None;
+// constants from the oracle program's ema update, PD_EMA_DECAY is -ln(2)/5921 * 1e9
+const EMA_DECAY: f64 = -117065e-9;
+const EMA_MAX_GAP: u64 = 4145;
+// about 7 half lives, when the cold start weighs under 1% of the ema
+pub const EMA_WARM_UP: u64 = 7 * 5921;
 
-    for txn in transactions.iter() {
-        if low == None || low.unwrap() > txn.price {
-            low = Some(txn.price)
-        }
-        if high == None || high.unwrap() < txn.price {
-            high = Some(txn.price)
-        }
-        if open_time == None || open_time.unwrap() > txn.block_time {
-            open_time = Some(txn.block_time);
-            open = Some(txn.price);
-        }
-        if close_time == None || close_time.unwrap() < txn.block_time {
-            close_time = Some(txn.block_time);
-            close = Some(txn.price);
-        }
+#[derive(Debug, Clone, Copy)]
+pub struct EmaTwap {
+    pub value: f64,
+    // slots of history since the ema last started
+    pub slots: u64,
+}
+impl EmaTwap {
+    pub fn is_warm(&self) -> bool {
+        self.slots >= EMA_WARM_UP
     }
-
-    let base: f64 = 10.0;
-    let scale_factor: f64 = base.powi(expo);
-
-    let open_time = open_time.unwrap() as f64;
-    let open_price = (open.unwrap() as f64) * scale_factor;
-    let high_price = (high.unwrap() as f64) * scale_factor;
-    let low_price = (low.unwrap() as f64) * scale_factor;
-    let close_price = (close.unwrap() as f64) * scale_factor;
-    let close_time = close_time.unwrap() as f64;
-
-    OHLC {
-        open_time: Some(open_time),
-        open: Some(open_price),
-        high: Some(high_price),
-        low: Some(low_price),
-        close: Some(close_price),
-        close_time: Some(close_time),
+}
+impl PriceSource for PythData {
+    fn ticks(&self) -> Vec<Tick> {
+        let scale_factor = 10f64.powi(self.expo);
+        self.data
+            .iter()
+            .map(|tx| Tick {
+                conf: Some(tx.conf as f64 * scale_factor),
+                slot: Some(tx.pub_slot),
+                ..Tick::new(
+                    tx.block_time as f64,
+                    price::Price::new(tx.price as i128, self.expo),
+                )
+            })
+            .collect()
     }
 }
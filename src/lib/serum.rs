@@ -1,5 +1,6 @@
-use super::candles::{CandleList, OHLC};
-use arr_macro::arr;
+use super::candles::{CandleList, CandleOptions, OHLC};
+use super::price::Price;
+use super::source::{PriceSource, Side, Tick};
 use chrono::prelude::DateTime;
 use chrono::Utc;
 use core::f64;
@@ -55,11 +56,11 @@ impl MarketResponse {
             }
             // reverse order
             candles[i].open = match x.iter().last() {
-                Some(i) => Some(i.price),
+                Some(i) => Price::from_f64(i.price),
                 None => None,
             };
             candles[i].close = match x.iter().next() {
-                Some(i) => Some(i.price),
+                Some(i) => Price::from_f64(i.price),
                 None => None,
             };
 
@@ -74,8 +75,8 @@ impl MarketResponse {
                     low = Some(y.price)
                 }
             }
-            candles[i].low = low;
-            candles[i].high = high;
+            candles[i].low = low.and_then(Price::from_f64);
+            candles[i].high = high.and_then(Price::from_f64);
         }
         Some(candles)
     }
@@ -100,88 +101,31 @@ pub struct SerumData {
     pub data: Vec<MarketData>,
 }
 impl SerumData {
-    pub fn get_candle_list(&self, start: &DateTime<Utc>) -> CandleList {
-        let mut candle_data: [Vec<MarketData>; 1440] = arr![Vec::new(); 1440];
-        let interval = chrono::Duration::seconds(60).num_milliseconds() as f64;
-        let start = (start.timestamp() * 1000) as f64;
-
-        for t in self.data.iter() {
-            //
-            // builds index and stores serum trades in vectors representing 1 min of data
-            // reverses order of candle so earliest price is 0 index
-            //
-            let i = (start - t.time) / interval;
-            let i = candle_data.len() - 1 - i as usize; // reverses the order of the candles
-            candle_data[i].push(t.clone());
-        }
-        let mut candles = [OHLC::new(); 1440];
-        //
-        // ------ TBD --------
-        // Need to implement logic in case prev candles close is the low/high
-        //
-        for (i, c) in candle_data.iter().enumerate() {
-            let mut candle = make_serum_candle(c);
-            if !candle.is_valid() {
-                if i != 0 && candles[i - 1].is_valid() {
-                    //
-                    //  if no data set fields to prev candles close price
-                    //
-                    candle = OHLC {
-                        open_time: None,
-                        open: candles[i - 1].close,
-                        high: candles[i - 1].close,
-                        low: candles[i - 1].close,
-                        close: candles[i - 1].close,
-                        close_time: None,
-                    }
-                }
-            }
-            //
-            // next candles open price should equal prev candles close price
-            //
-            if i != 0 && candles[i - 1].close != None {
-                candle.open = candles[i - 1].close;
-            }
-            candles[i] = candle
-        }
-        return CandleList::new(candles);
+    pub fn get_candle_list(
+        &self,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        options: &CandleOptions,
+    ) -> CandleList {
+        self.candles(start, end, options)
     }
 }
-fn make_serum_candle(trades: &Vec<MarketData>) -> OHLC {
-    if trades.len() == 0 {
-        return OHLC::new();
-    }
-
-    let mut open_time: Option<f64> = None;
-    let mut open: Option<f64> = None;
-    let mut high: Option<f64> = None;
-    let mut low: Option<f64> = None;
-    let mut close_time: Option<f64> = None;
-    let mut close: Option<f64> = None;
-
-    for t in trades.iter() {
-        if low == None || low.unwrap() > t.price {
-            low = Some(t.price)
-        }
-        if high == None || high.unwrap() < t.price {
-            high = Some(t.price)
-        }
-        if open_time == None || open_time.unwrap() > t.price {
-            open_time = Some(t.time);
-            open = Some(t.price);
-        }
-        if close_time == None || close_time.unwrap() < t.price {
-            close_time = Some(t.time);
-            close = Some(t.price);
-        }
-    }
-
-    OHLC {
-        open_time,
-        open,
-        high,
-        low,
-        close,
-        close_time,
+impl PriceSource for SerumData {
+    fn ticks(&self) -> Vec<Tick> {
+        self.data
+            .iter()
+            .filter_map(|t| {
+                Some(Tick {
+                    size: Some(t.size),
+                    side: match t.side.as_str() {
+                        "buy" => Some(Side::Buy),
+                        "sell" => Some(Side::Sell),
+                        _ => None,
+                    },
+                    // bonfida times are in milliseconds
+                    ..Tick::new(t.time / 1000.0, Price::from_f64(t.price)?)
+                })
+            })
+            .collect()
     }
 }
@@ -0,0 +1,294 @@
+use super::candles::{
+    bucket_ticks, time_weighted_average, CandleList, CandleOptions, Interval, TwapEstimate,
+    TwapMethod, OHLC,
+};
+use super::filter::filter_ticks;
+use super::price::Price;
+use chrono::prelude::DateTime;
+use chrono::Utc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+// a single price observation from any venue
+#[derive(Debug, Clone, Copy)]
+pub struct Tick {
+    // unix timestamp in seconds
+    pub time: f64,
+    pub price: Price,
+    // oracle only
+    pub conf: Option<f64>,
+    pub slot: Option<u64>,
+    // exchange only
+    pub size: Option<f64>,
+    pub side: Option<Side>,
+}
+impl Tick {
+    pub fn new(time: f64, price: Price) -> Self {
+        Self {
+            time,
+            price,
+            conf: None,
+            slot: None,
+            size: None,
+            side: None,
+        }
+    }
+}
+
+//
+// anything that can produce a list of ticks can be turned into candles and averages,
+// a new venue only needs to implement ticks. the options filters run once over every
+// tick before any window is taken, so they have history at the start of the window
+// and candles, twaps and counts over the same window all see the same ticks
+//
+pub trait PriceSource {
+    // ticks in any order
+    fn ticks(&self) -> Vec<Tick>;
+
+    // candles from the ticks in [start, end)
+    fn candles(
+        &self,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        options: &CandleOptions,
+    ) -> CandleList {
+        let (ticks, report) = filter_ticks(&self.ticks(), &options.filters);
+        build_filtered_candles(&window(&ticks, start, end), end.timestamp(), options)
+            .with_filter_report(report)
+    }
+    fn tick_bars(&self, count: usize, options: &CandleOptions) -> Vec<OHLC> {
+        build_tick_bars(&self.ticks(), count, options)
+    }
+    fn range_bars(&self, range: Price, options: &CandleOptions) -> Vec<OHLC> {
+        build_range_bars(&self.ticks(), range, options)
+    }
+    // time weighted price over [start, end) compared against the candle estimate
+    fn twap(
+        &self,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        interval: &Interval,
+        method: &TwapMethod,
+        options: &CandleOptions,
+    ) -> TwapEstimate {
+        let (ticks, _) = filter_ticks(&self.ticks(), &options.filters);
+        let pairs: Vec<(f64, Price)> = ticks.iter().map(|t| (t.time, t.price)).collect();
+        let time_weighted =
+            time_weighted_average(&pairs, start.timestamp() as f64, end.timestamp() as f64);
+
+        let window = window(&ticks, start, end);
+        let ohlc4 = build_filtered_candles(&window, end.timestamp(), options).twap(
+            interval,
+            method,
+            end.timestamp(),
+        );
+        TwapEstimate {
+            time_weighted,
+            ohlc4,
+        }
+    }
+    // how many different prices the ticks in [start, end) kept by the filters had
+    fn distinct_prices(
+        &self,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        options: &CandleOptions,
+    ) -> usize {
+        let (ticks, _) = filter_ticks(&self.ticks(), &options.filters);
+        let mut prices: Vec<Price> = window(&ticks, start, end).iter().map(|t| t.price).collect();
+        prices.sort();
+        prices.dedup();
+        prices.len()
+    }
+    // volume weighted price of the ticks in [start, end) that carry a size
+    fn vwap(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> Option<f64> {
+        let mut volume = 0.0;
+        let mut notional = 0.0;
+        for t in window(&self.ticks(), start, end).iter() {
+            if let Some(size) = t.size {
+                volume += size;
+                notional += t.price.to_f64() * size;
+            }
+        }
+        if volume == 0.0 {
+            return None;
+        }
+        Some(notional / volume)
+    }
+    //
+    // price over [start, end) with each update weighted by 1/conf^2, so periods
+    // where publishers disagreed count for less. a zero conf is treated as the
+    // smallest unit of the price so it doesnt take all the weight
+    //
+    fn conf_weighted_price(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> Option<f64> {
+        let mut weighted = 0.0;
+        let mut weights = 0.0;
+        for t in window(&self.ticks(), start, end).iter() {
+            let conf = match t.conf {
+                Some(conf) => conf.max(10f64.powi(t.price.expo)),
+                None => continue,
+            };
+            let weight = 1.0 / (conf * conf);
+            weighted += t.price.to_f64() * weight;
+            weights += weight;
+        }
+        if weights == 0.0 {
+            return None;
+        }
+        Some(weighted / weights)
+    }
+}
+
+// ticks with start <= time < end
+pub fn window(ticks: &[Tick], start: &DateTime<Utc>, end: &DateTime<Utc>) -> Vec<Tick> {
+    let (start, end) = (start.timestamp() as f64, end.timestamp() as f64);
+    ticks
+        .iter()
+        .filter(|t| t.time >= start && t.time < end)
+        .copied()
+        .collect()
+}
+
+// sorts ticks into slots of options.resolution and builds a candle for each one
+pub fn build_candles(ticks: &[Tick], end: i64, options: &CandleOptions) -> CandleList {
+    let (ticks, report) = filter_ticks(ticks, &options.filters);
+    build_filtered_candles(&ticks, end, options).with_filter_report(report)
+}
+
+//
+// same as build_candles for ticks that already went through options.filters. the
+// filters keep state, so running them a second time would drop more ticks
+//
+pub fn build_filtered_candles(ticks: &[Tick], end: i64, options: &CandleOptions) -> CandleList {
+    let interval = options.resolution.num_seconds();
+    let (first, candle_data) =
+        bucket_ticks(ticks, |t| t.time.floor() as i64, end, interval, options);
+    let candles: Vec<OHLC> = candle_data.iter().map(|c| make_candle(c)).collect();
+    CandleList::new(first, interval, candles, options)
+}
+
+//
+// same as build_candles but bucketed by slot, start and resolution of the list are
+// slot numbers so use CandleList::group to combine them. ticks without a slot are skipped
+//
+pub fn build_slot_candles(ticks: &[Tick], slots: u64, options: &CandleOptions) -> CandleList {
+    let slots = slots.max(1) as i64;
+    let ticks: Vec<Tick> = ticks.iter().filter(|t| t.slot.is_some()).copied().collect();
+    let (ticks, report) = filter_ticks(&ticks, &options.filters);
+    let end = match ticks.iter().filter_map(|t| t.slot).max() {
+        Some(slot) => slot as i64 + 1,
+        None => 0,
+    };
+    let (first, candle_data) =
+        bucket_ticks(&ticks, |t| t.slot.unwrap() as i64, end, slots, options);
+    let candles: Vec<OHLC> = candle_data.iter().map(|c| make_candle(c)).collect();
+    CandleList::new(first, slots, candles, options).with_filter_report(report)
+}
+
+//
+// bars that close every count ticks instead of on the clock, which evens out bursts
+// of pyth updates. only the filters in options are used, the last bar can be short
+//
+pub fn build_tick_bars(ticks: &[Tick], count: usize, options: &CandleOptions) -> Vec<OHLC> {
+    let (ticks, _) = filter_ticks(ticks, &options.filters);
+    ticks.chunks(count.max(1)).map(make_candle).collect()
+}
+
+//
+// bars that close once the price has covered range, a tick that would stretch the
+// bars high - low past range opens the next bar. only the filters in options are used
+//
+pub fn build_range_bars(ticks: &[Tick], range: Price, options: &CandleOptions) -> Vec<OHLC> {
+    let (ticks, _) = filter_ticks(ticks, &options.filters);
+    let mut bars = Vec::new();
+    let mut first = 0;
+    let (mut high, mut low) = (None, None);
+    for (i, t) in ticks.iter().enumerate() {
+        let h = high.map_or(t.price, |h: Price| h.max(t.price));
+        let l = low.map_or(t.price, |l: Price| l.min(t.price));
+        // a spread too wide to hold in the mantissa is past any range
+        if h.checked_sub(&l).is_none_or(|d| d > range) && i > first {
+            bars.push(make_candle(&ticks[first..i]));
+            first = i;
+            high = Some(t.price);
+            low = Some(t.price);
+        } else {
+            high = Some(h);
+            low = Some(l);
+        }
+    }
+    if first < ticks.len() {
+        bars.push(make_candle(&ticks[first..]));
+    }
+    bars
+}
+
+pub fn make_candle(ticks: &[Tick]) -> OHLC {
+    if ticks.is_empty() {
+        return OHLC::new();
+    }
+    //
+    // order by time then slot, several pyth updates can land in the same second.
+    // the sort is stable so ticks that still tie keep the order they arrived in
+    //
+    let mut ticks = ticks.to_vec();
+    ticks.sort_by(|a, b| (a.time, a.slot).partial_cmp(&(b.time, b.slot)).unwrap());
+    let first = ticks[0];
+    let last = ticks[ticks.len() - 1];
+
+    let mut high = first.price;
+    let mut low = first.price;
+    for t in ticks.iter() {
+        high = high.max(t.price);
+        low = low.min(t.price);
+    }
+    let mut candle = OHLC {
+        open_time: Some(first.time),
+        open: Some(first.price),
+        high: Some(high),
+        low: Some(low),
+        close: Some(last.price),
+        close_time: Some(last.time),
+        ..OHLC::new()
+    };
+
+    // trades carry a size, oracle updates dont
+    if ticks.iter().any(|t| t.size.is_some()) {
+        let mut volume = 0.0;
+        let mut notional = 0.0;
+        let mut buy_volume = 0.0;
+        let mut sell_volume = 0.0;
+        for t in ticks.iter() {
+            let size = t.size.unwrap_or(0.0);
+            volume += size;
+            notional += t.price.to_f64() * size;
+            match t.side {
+                Some(Side::Buy) => buy_volume += size,
+                Some(Side::Sell) => sell_volume += size,
+                None => {}
+            }
+        }
+        candle.volume = Some(volume);
+        candle.quote_volume = Some(notional);
+        candle.buy_volume = Some(buy_volume);
+        candle.sell_volume = Some(sell_volume);
+        if volume > 0.0 {
+            candle.vwap = Some(notional / volume);
+        }
+        candle.trades = Some(ticks.len() as u64);
+    } else {
+        candle.updates = Some(ticks.len() as u64);
+    }
+
+    let confs: Vec<f64> = ticks.iter().filter_map(|t| t.conf).collect();
+    if !confs.is_empty() {
+        candle.conf_low = Some(confs.iter().cloned().fold(f64::INFINITY, f64::min));
+        candle.conf_high = Some(confs.iter().cloned().fold(f64::NEG_INFINITY, f64::max));
+        candle.conf_mean = Some(confs.iter().sum::<f64>() / confs.len() as f64);
+    }
+    candle
+}
@@ -0,0 +1,163 @@
+use super::candles::{Alignment, CandleOptions, GapFill, OHLC};
+use super::filter::{FilterReport, TickFilters};
+use super::price::Price;
+use super::source::{make_candle, Tick};
+
+//
+// builds candles one tick at a time for long running processes. a candle is returned
+// as soon as a tick lands in a later slot, together with the open time of its slot.
+// the running twap and vwap cover every tick since the stream started
+//
+pub struct CandleStream {
+    options: CandleOptions,
+    filters: TickFilters,
+    anchor: Option<i64>,
+    slot: i64,
+    ticks: Vec<Tick>,
+    prev: Option<OHLC>,
+    last_tick: Option<Tick>,
+    weighted: f64,
+    elapsed: f64,
+    volume: f64,
+    notional: f64,
+}
+impl CandleStream {
+    pub fn new(options: &CandleOptions) -> Self {
+        Self {
+            options: options.clone(),
+            filters: TickFilters::new(&options.filters),
+            anchor: None,
+            slot: 0,
+            ticks: Vec::new(),
+            prev: None,
+            last_tick: None,
+            weighted: 0.0,
+            elapsed: 0.0,
+            volume: 0.0,
+            notional: 0.0,
+        }
+    }
+    //
+    // adds a tick and returns every candle that closed because of it. ticks older than
+    // the last one are ignored since their candle may already be out, and ticks the
+    // filters reject are counted in filter_report
+    //
+    pub fn push(&mut self, tick: Tick) -> Vec<(i64, OHLC)> {
+        if let Some(last) = self.last_tick {
+            if tick.time < last.time {
+                return Vec::new();
+            }
+        }
+        if !self.filters.accept(&tick) {
+            return Vec::new();
+        }
+        if self.anchor.is_none() {
+            // with nothing to count back from, window alignment starts at the first tick
+            let resolution = self.options.resolution.num_seconds();
+            self.anchor = Some(match self.options.alignment {
+                Alignment::Window => tick.time.floor() as i64,
+                Alignment::Epoch => self.options.alignment.anchor(0, resolution),
+            });
+            self.slot = self.slot_of(tick.time);
+        }
+        self.update_averages(&tick);
+
+        let closed = self.close_until(self.slot_of(tick.time), Some(tick.price));
+        self.ticks.push(tick);
+        closed
+    }
+    // closes every slot before the one time falls in, for when no ticks have arrived
+    pub fn flush(&mut self, time: f64) -> Vec<(i64, OHLC)> {
+        if self.anchor.is_none() {
+            return Vec::new();
+        }
+        self.close_until(self.slot_of(time), None)
+    }
+    // the candle still being built
+    pub fn current(&self) -> OHLC {
+        make_candle(&self.ticks)
+    }
+    pub fn filter_report(&self) -> &FilterReport {
+        self.filters.report()
+    }
+    pub fn twap(&self) -> Option<f64> {
+        if self.elapsed > 0.0 {
+            return Some(self.weighted / self.elapsed);
+        }
+        self.last_tick.map(|t| t.price.to_f64())
+    }
+    pub fn vwap(&self) -> Option<f64> {
+        if self.volume == 0.0 {
+            return None;
+        }
+        Some(self.notional / self.volume)
+    }
+
+    fn slot_of(&self, time: f64) -> i64 {
+        let resolution = self.options.resolution.num_seconds();
+        (time.floor() as i64 - self.anchor.unwrap()).div_euclid(resolution)
+    }
+    fn update_averages(&mut self, tick: &Tick) {
+        if let Some(last) = self.last_tick {
+            self.weighted += last.price.to_f64() * (tick.time - last.time);
+            self.elapsed += tick.time - last.time;
+        }
+        if let Some(size) = tick.size {
+            self.volume += size;
+            self.notional += tick.price.to_f64() * size;
+        }
+        self.last_tick = Some(*tick);
+    }
+    //
+    // closes the slot in progress and any empty slots up to slot. next is the price
+    // that reopens the stream, used to interpolate across the gap
+    //
+    fn close_until(&mut self, slot: i64, next: Option<Price>) -> Vec<(i64, OHLC)> {
+        let mut closed = Vec::new();
+        if slot <= self.slot {
+            return closed;
+        }
+        let mut gaps = slot - self.slot - 1;
+        if self.ticks.is_empty() {
+            gaps += 1;
+        } else {
+            let candle = make_candle(&self.ticks);
+            self.ticks.clear();
+            self.emit(self.slot, candle, &mut closed);
+        }
+
+        for i in 0..gaps {
+            let prev = self.prev.and_then(|c| c.close);
+            let candle = match (self.options.gap_fill, prev, next) {
+                (GapFill::ForwardFill, Some(p), _) => OHLC::synthetic(p, p),
+                (GapFill::Interpolate, Some(p), Some(n)) => {
+                    let step = n
+                        .checked_sub(&p)
+                        .and_then(|d| p.checked_add(&d.div_int((gaps - i + 1) as i128)));
+                    OHLC::synthetic(p, step.unwrap_or(p))
+                }
+                (GapFill::Interpolate, Some(p), None) => OHLC::synthetic(p, p),
+                _ => OHLC::new(),
+            };
+            self.emit(slot - gaps + i, candle, &mut closed);
+        }
+        self.slot = slot;
+        closed
+    }
+    fn emit(&mut self, slot: i64, mut candle: OHLC, closed: &mut Vec<(i64, OHLC)>) {
+        //
+        // next candles open price should equal prev candles close price
+        //
+        if let Some(close) = self.prev.and_then(|p| p.close) {
+            if candle.is_valid() {
+                candle.reopen(close);
+            }
+        }
+        self.prev = Some(candle);
+        if self.options.gap_fill == GapFill::Drop && !candle.is_valid() {
+            return;
+        }
+        let start = self.anchor.unwrap() + slot * self.options.resolution.num_seconds();
+        closed.push((start, candle));
+    }
+}
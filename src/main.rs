@@ -1,13 +1,20 @@
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use dialoguer::{theme::ColorfulTheme, Input, Select};
 use sol_wap::candles;
+use sol_wap::cross;
+use sol_wap::divergence;
+use sol_wap::filter;
+use sol_wap::price::Price;
 use sol_wap::pyth;
+use sol_wap::serum;
+use sol_wap::source::PriceSource;
 use sol_wap::{PythClient, SerumClient};
 use std::error::Error;
+use std::fs;
 use std::process;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let twap_options = ["Pyth", "Serum"];
+    let twap_options = ["Pyth", "Pyth cross pair", "Serum", "Pyth vs Serum"];
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("TWAP Option")
         .default(0)
@@ -17,13 +24,168 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     match twap_options[selection] {
         "Pyth" => pyth_twap()?,
+        "Pyth cross pair" => pyth_cross_twap()?,
         "Serum" => serum_twap()?,
+        "Pyth vs Serum" => pyth_serum_divergence()?,
         _ => panic!("Not a valid option"),
     };
     Ok(())
 }
 
 fn pyth_twap() -> Result<(), Box<dyn Error>> {
+    let pyth = select_pyth_client();
+    let products = pyth.get_product_accounts()?;
+
+    let px_acct = select_product(&products, "Symbol Option");
+
+    let px_data = match pyth.get_price_account_data(px_acct) {
+        Ok(i) => i,
+        Err(e) => panic!("Pyth Err: {}", e),
+    };
+
+    let (start, end) = select_time_window(select_pyth_window());
+    let (resolution, pyth_candle) = pyth_candle_sizes(end - start);
+
+    let run_start = Utc::now();
+    let historic_prices = match pyth.get_historical_data(&px_data, start, end) {
+        Ok(i) => i,
+        Err(e) => panic!("Pyth Err: {}", e),
+    };
+
+    let print_interval = if pyth_candle.num_seconds() < 60 {
+        pyth_candle
+    } else {
+        candles::Interval::MIN1
+    };
+    let options = select_candle_options(resolution);
+
+    let buckets = ["Block time", "Pub slot", "Tick count", "Price range"];
+    let bucket = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Bucket candles by")
+        .default(0)
+        .items(&buckets)
+        .interact()
+        .unwrap();
+    let candles = historic_prices.get_pyth_candles(&start, &end, &options);
+    match buckets[bucket] {
+        "Pub slot" => {
+            let slots: usize = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Slots per candle")
+                .default(25)
+                .interact_text()
+                .unwrap();
+            let slot_candles = historic_prices.get_slot_candles(1, &options);
+            candles::print_candles(&slot_candles.group(slots));
+            print_filter_report(&options, slot_candles.filter_report());
+        }
+        "Tick count" => {
+            let count: usize = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Ticks per bar")
+                .default(50)
+                .interact_text()
+                .unwrap();
+            print_styled_candles(historic_prices.tick_bars(count, &options));
+        }
+        "Price range" => {
+            let range: f64 = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Price move per bar")
+                .interact_text()
+                .unwrap();
+            let range = Price::from_f64(range).ok_or("price move should be a number")?;
+            print_styled_candles(historic_prices.range_bars(range, &options));
+        }
+        _ => {
+            print_styled_candles(candles.get_candles(&print_interval));
+            print_filter_report(&options, candles.filter_report());
+        }
+    }
+
+    let method = select_twap_method();
+    let twap = historic_prices.twap(&start, &end, &pyth_candle, &method, &options);
+    print_twap(&twap, &pyth_candle);
+    let distinct = historic_prices.distinct_prices(&start, &end, &options);
+    print_stats(&candles, &twap, Some(distinct));
+    print_volatility(&candles, &pyth_candle);
+    rolling_series(&candles, &pyth_candle, &method)?;
+    if let Some(price) = historic_prices.conf_weighted_price(&start, &end) {
+        println!("Confidence weighted: ${:.4}", price);
+    }
+    if let Some(ema) = historic_prices.ema_twap() {
+        let onchain = px_data.onchain_twap();
+        println!(
+            "Pyth EMA TWAP: ${:.4} computed, ${} on-chain, diff ${:.4}",
+            ema.value,
+            onchain,
+            ema.value - onchain.to_f64()
+        );
+        if !ema.is_warm() {
+            println!(
+                "EMA started cold {} slots ago, it needs about {} slots to match on-chain",
+                ema.slots,
+                pyth::EMA_WARM_UP
+            );
+        }
+    }
+    println!("N: {} pyth transactions", historic_prices.data.len());
+    let pyth_duration = Utc::now() - run_start;
+    let (hrs, mins, secs) = (
+        pyth_duration.num_hours(),
+        pyth_duration.num_minutes() % 60,
+        pyth_duration.num_seconds() % 60,
+    );
+    println!("Executed in {}:{}:{}", hrs, mins, secs);
+    Ok(())
+}
+
+// twap of base/quote built from two pyth products, e.g. SOL/BTC from SOL/USD and BTC/USD
+fn pyth_cross_twap() -> Result<(), Box<dyn Error>> {
+    let pyth = select_pyth_client();
+    let products = pyth.get_product_accounts()?;
+    let base = select_product(&products, "Base symbol");
+    let quote = select_product(&products, "Quote symbol");
+    let (start, end) = select_time_window(select_pyth_window());
+    let (resolution, pyth_candle) = pyth_candle_sizes(end - start);
+
+    let (base, quote) = match pyth.get_cross_data(base, quote, start, end) {
+        Ok(i) => i,
+        Err(e) => panic!("Pyth Err: {}", e),
+    };
+
+    let options = select_candle_options(resolution);
+    let gaps = [cross::CrossGap::Skip, cross::CrossGap::HoldLast];
+    let gap = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("When only one leg has a price")
+        .default(0)
+        .items(&gaps)
+        .interact()
+        .unwrap();
+
+    let (candles, report) = cross::cross_candles(
+        &base.get_pyth_candles(&start, &end, &options),
+        &quote.get_pyth_candles(&start, &end, &options),
+        gaps[gap],
+    )?;
+    candles::print_candles(&candles.get_candles(&pyth_candle));
+    println!("Legs: {}", report);
+
+    let method = select_twap_method();
+    let twap = candles::TwapEstimate {
+        time_weighted: None,
+        ohlc4: candles.twap(&pyth_candle, &method, end.timestamp()),
+    };
+    print_twap(&twap, &pyth_candle);
+    print_stats(&candles, &twap, None);
+    print_volatility(&candles, &pyth_candle);
+    rolling_series(&candles, &pyth_candle, &method)?;
+    println!(
+        "N: {} base and {} quote pyth transactions",
+        base.data.len(),
+        quote.data.len()
+    );
+    Ok(())
+}
+
+fn select_pyth_client() -> PythClient {
     let networks = ["Mainnet Beta", "Devnet", "Localnet"];
     let network = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Network Selection")
@@ -31,7 +193,7 @@ fn pyth_twap() -> Result<(), Box<dyn Error>> {
         .items(&networks)
         .interact()
         .unwrap();
-    let pyth = match networks[network] {
+    match networks[network] {
         "Mainnet Beta" => {
             println!("Pyth is currently only on devnet");
             process::exit(1);
@@ -56,19 +218,27 @@ fn pyth_twap() -> Result<(), Box<dyn Error>> {
             PythClient::new(url, pyth_map_key)
         }
         _ => panic!("Not a valid network option"),
-    };
-
-    let products = pyth.get_product_accounts()?;
+    }
+}
 
+fn select_product(products: &[pyth::ProductResult], prompt: &str) -> [u8; 32] {
+    let filter = select_product_filter(products);
+    let filtered = pyth::filter_products(products, &filter);
+    let products = if filtered.is_empty() {
+        println!("No products match, showing all");
+        products
+    } else {
+        &filtered
+    };
     let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Symbol Option")
+        .with_prompt(prompt)
         .default(0)
-        .items(&products)
+        .items(products)
         .paged(true)
         .interact()
         .unwrap();
 
-    let px_acct = match pyth::find_product(&products, products[selection].to_string()) {
+    match pyth::find_product(products, products[selection].to_string()) {
         Some(i) => i,
         None => {
             for p in products.iter() {
@@ -76,15 +246,74 @@ fn pyth_twap() -> Result<(), Box<dyn Error>> {
             }
             panic!("Couldnt find symbol in list of pyth products");
         }
-    };
+    }
+}
 
-    let px_data = match pyth.get_price_account_data(px_acct) {
-        Ok(i) => i,
-        Err(e) => panic!("Pyth Err: {}", e),
+// narrows the symbol list by asset type and quote currency
+fn select_product_filter(products: &[pyth::ProductResult]) -> pyth::ProductFilter {
+    let asset_type = select_attribute(products, "asset_type", "Asset type");
+    let filter = pyth::ProductFilter {
+        asset_type,
+        ..Default::default()
     };
+    // only offer currencies that some product of the chosen type is quoted in
+    let quote_currency = select_attribute(
+        &pyth::filter_products(products, &filter),
+        "quote_currency",
+        "Quoted in",
+    );
+    pyth::ProductFilter {
+        quote_currency,
+        ..filter
+    }
+}
+
+// one of the values the products have for key, or none for all of them
+fn select_attribute(products: &[pyth::ProductResult], key: &str, prompt: &str) -> Option<String> {
+    let mut values = pyth::attribute_values(products, key);
+    if values.is_empty() {
+        return None;
+    }
+    values.insert(0, String::from("All"));
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(0)
+        .items(&values)
+        .interact()
+        .unwrap();
+    match selection {
+        0 => None,
+        i => Some(values[i].clone()),
+    }
+}
 
+// an explicit utc window, or the last duration up to now when left blank
+fn select_time_window(duration: Duration) -> (DateTime<Utc>, DateTime<Utc>) {
+    let window: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!(
+            "Window, e.g. 2021-07-02 12:00 to 18:00 UTC (blank for the last {})",
+            candles::Interval::from_duration(duration).unwrap()
+        ))
+        .allow_empty(true)
+        .validate_with(|s: &String| -> Result<(), &str> {
+            if s.trim().is_empty() {
+                return Ok(());
+            }
+            sol_wap::parse_window(s).map(|_| ())
+        })
+        .interact_text()
+        .unwrap();
+    if window.trim().is_empty() {
+        let end = Utc::now();
+        return (end - duration, end);
+    }
+    sol_wap::parse_window(&window).unwrap()
+}
+
+// how far back to fetch pyth data when no explicit window is given
+fn select_pyth_window() -> Duration {
     let pyth_intervals = [
-        // "1 minute", // not enough data to make it worthwhile
+        "1 minute",
         "5 minutes",
         "15 minutes",
         "1 hour",
@@ -99,47 +328,110 @@ fn pyth_twap() -> Result<(), Box<dyn Error>> {
         .interact()
         .unwrap();
 
-    let (duration, pyth_candle) = match pyth_intervals[interval_selection] {
-        "1 minute" => (Duration::minutes(1), candles::Interval::MIN1),
-        "5 minutes" => (Duration::minutes(5), candles::Interval::MIN1),
-        "15 minutes" => (Duration::minutes(15), candles::Interval::MIN1),
-        "1 hour" => (Duration::minutes(60), candles::Interval::MIN15),
-        "4 hour" => (Duration::minutes(240), candles::Interval::HR1),
-        "1 day (slow)" => (Duration::minutes(1440), candles::Interval::HR1),
-        _ => (Duration::minutes(1), candles::Interval::MIN1),
+    match pyth_intervals[interval_selection] {
+        "1 minute" => Duration::minutes(1),
+        "5 minutes" => Duration::minutes(5),
+        "15 minutes" => Duration::minutes(15),
+        "1 hour" => Duration::minutes(60),
+        "4 hour" => Duration::minutes(240),
+        "1 day (slow)" => Duration::minutes(1440),
+        _ => Duration::minutes(1),
+    }
+}
+
+//
+// slot resolution and twap candle size for the length of the window. pyth updates
+// about every slot so short windows use second candles
+//
+fn pyth_candle_sizes(span: Duration) -> (candles::Interval, candles::Interval) {
+    let pyth_candle = match span.num_minutes() {
+        m if m <= 1 => candles::Interval::SEC5,
+        m if m <= 5 => candles::Interval::SEC30,
+        m if m <= 15 => candles::Interval::MIN1,
+        m if m <= 60 => candles::Interval::MIN15,
+        _ => candles::Interval::HR1,
+    };
+    let resolution = if pyth_candle.num_seconds() < 60 {
+        candles::Interval::SEC1
+    } else {
+        candles::Interval::MIN1
     };
+    (resolution, pyth_candle)
+}
+
+fn serum_twap() -> Result<(), Box<dyn Error>> {
+    let trades = select_serum_trades("Symbol Option")?;
+    let (start, end) = select_time_window(Duration::days(1));
+    warn_first_trade(&trades, &start);
+    let options = select_candle_options(candles::Interval::MIN1);
+    let candles = trades.get_candle_list(&start, &end, &options);
+    let candle_interval: candles::Interval = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Candle interval (e.g. 30m, 2h, 1d)")
+        .default(candles::Interval::HR1)
+        .interact_text()
+        .unwrap();
+    let candles_interval = candles.get_candles(&candle_interval);
+
+    println!("{}", candle_interval);
+    print_styled_candles(candles_interval);
+    print_filter_report(&options, candles.filter_report());
+
+    let method = select_twap_method();
+    let twap = trades.twap(&start, &end, &candle_interval, &method, &options);
+    print_twap(&twap, &candle_interval);
+    let distinct = trades.distinct_prices(&start, &end, &options);
+    print_stats(&candles, &twap, Some(distinct));
+    print_volatility(&candles, &candle_interval);
+    rolling_series(&candles, &candle_interval, &method)?;
+    if let Some(vwap) = trades.vwap(&start, &end) {
+        println!("VWAP: ${:.4}", vwap);
+    }
+    println!("N: {} serum trades", trades.data.len());
+    Ok(())
+}
 
-    let start_time = Utc::now();
-    let historic_prices = match pyth.get_historical_data(px_data.key, start_time, duration) {
+// per candle basis between a pyth product and a serum market for the same asset
+fn pyth_serum_divergence() -> Result<(), Box<dyn Error>> {
+    let pyth = select_pyth_client();
+    let products = pyth.get_product_accounts()?;
+    let px_acct = select_product(&products, "Pyth symbol");
+    let px_data = match pyth.get_price_account_data(px_acct) {
         Ok(i) => i,
         Err(e) => panic!("Pyth Err: {}", e),
     };
+    let trades = select_serum_trades("Serum market")?;
 
-    let candles = historic_prices.get_pyth_candles(&start_time, px_data.expo);
-    let candles_1min = candles.get_candles(&candles::Interval::MIN1);
-
-    candles::print_candles(&candles_1min);
+    let (start, end) = select_time_window(select_pyth_window());
+    let (resolution, pyth_candle) = pyth_candle_sizes(end - start);
+    warn_first_trade(&trades, &start);
+    let historic_prices = match pyth.get_historical_data(&px_data, start, end) {
+        Ok(i) => i,
+        Err(e) => panic!("Pyth Err: {}", e),
+    };
 
-    let twap = candles.twap(&pyth_candle).unwrap();
-    println!("TWAP: ${:.2} using {} candles", twap, &pyth_candle);
-    println!("N: {} pyth transactions", historic_prices.data.len());
-    let pyth_duration = Utc::now() - start_time;
-    let (hrs, mins, secs) = (
-        pyth_duration.num_hours(),
-        pyth_duration.num_minutes() % 60,
-        pyth_duration.num_seconds() % 60,
+    let options = select_candle_options(resolution);
+    let (points, report) = divergence::divergence(
+        &historic_prices.get_pyth_candles(&start, &end, &options),
+        &trades.get_candle_list(&start, &end, &options),
+        &pyth_candle,
+    );
+    divergence::print_divergence(&points);
+    println!("Divergence ({} candles): {}", pyth_candle, report);
+    println!(
+        "N: {} pyth transactions and {} serum trades",
+        historic_prices.data.len(),
+        trades.data.len()
     );
-    println!("Executed in {}:{}:{}", hrs, mins, secs);
     Ok(())
 }
 
-fn serum_twap() -> Result<(), Box<dyn Error>> {
+fn select_serum_trades(prompt: &str) -> Result<serum::SerumData, Box<dyn Error>> {
     let s = SerumClient::new();
 
     let markets = s.get_markets()?;
 
     let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Symbol Option")
+        .with_prompt(prompt)
         .default(0)
         .items(&markets)
         .paged(true)
@@ -149,20 +441,229 @@ fn serum_twap() -> Result<(), Box<dyn Error>> {
         .to_ascii_uppercase()
         .replace(&['/'][..], ""); // remove backslash if provided
 
-    let trades = match s.get_trades(&symbol) {
-        Some(i) => i,
+    match s.get_trades(&symbol) {
+        Some(i) => Ok(i),
         None => panic!("failed to get trades"),
+    }
+}
+
+// bonfida only returns the most recent trades
+fn warn_first_trade(trades: &serum::SerumData, start: &DateTime<Utc>) {
+    let first_trade = match trades
+        .ticks()
+        .iter()
+        .map(|t| t.time)
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+    {
+        Some(t) => t,
+        None => {
+            println!("No trades returned");
+            return;
+        }
     };
-    let start_time = Utc::now();
-    let candles = trades.get_candle_list(&start_time);
-    let candle_interval = candles::Interval::HR1;
-    let candles_1hr = candles.get_candles(&candle_interval);
+    if first_trade > start.timestamp() as f64 {
+        println!(
+            "Trades only go back to {}",
+            sol_wap::utc_to_datetime(first_trade as i64)
+        );
+    }
+}
 
-    println!("1 HR");
-    candles::print_candles(&candles_1hr);
+fn print_twap(twap: &candles::TwapEstimate, interval: &candles::Interval) {
+    let result = &twap.ohlc4;
+    match result.value {
+        Some(t) => println!(
+            "TWAP: ${:.2} {} of {} {} candles ({} real, {} synthetic)",
+            t,
+            result.method,
+            result.candles,
+            interval,
+            result.real(),
+            result.synthetic
+        ),
+        None => println!("TWAP: no valid {} candles", interval),
+    }
+    if let (Some(first), Some(last)) = (result.first_tick, result.last_tick) {
+        println!(
+            "Data: {} ticks from {} to {}, largest gap {:.0}s, last tick {:.0}s old",
+            result.ticks,
+            sol_wap::utc_to_datetime(first as i64),
+            sol_wap::utc_to_datetime(last as i64),
+            result.largest_gap,
+            result.staleness.unwrap_or(0.0)
+        );
+    }
+    if let Some(t) = twap.time_weighted {
+        println!("TWAP: ${:.4} weighted by time between ticks", t);
+    }
+    if let Some(d) = twap.difference() {
+        println!("Diff: ${:.4} candle estimate vs tick weighted", d);
+    }
+}
 
-    let twap = candles.twap(&candle_interval).unwrap();
-    println!("TWAP: ${:.2} using {} candles", twap, &candle_interval);
-    println!("N: {} serum trades", trades.data.len());
+// distinct is the number of distinct tick prices when the ticks are at hand
+fn print_stats(
+    candles: &candles::CandleList,
+    twap: &candles::TwapEstimate,
+    distinct: Option<usize>,
+) {
+    if let Some(stats) = twap.ohlc4.value.and_then(|t| candles.stats(&t)) {
+        let stats = match distinct {
+            Some(n) => stats.with_distinct_ticks(n),
+            None => stats,
+        };
+        println!("Stats: {}", stats);
+    }
+}
+
+// prints the candles as they are or as heikin-ashi candles
+fn print_styled_candles(ohlc: Vec<candles::OHLC>) {
+    let styles = ["OHLC", "Heikin-Ashi"];
+    let style = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Candle style")
+        .default(0)
+        .items(&styles)
+        .interact()
+        .unwrap();
+    match styles[style] {
+        "Heikin-Ashi" => candles::print_candles(&candles::heikin_ashi(&ohlc)),
+        _ => candles::print_candles(&ohlc),
+    }
+}
+
+fn print_volatility(candles: &candles::CandleList, interval: &candles::Interval) {
+    let estimators = [
+        candles::Volatility::CloseToClose,
+        candles::Volatility::Parkinson,
+        candles::Volatility::GarmanKlass,
+        candles::Volatility::RogersSatchell,
+    ];
+    let values: Vec<String> = estimators
+        .iter()
+        .filter_map(|e| {
+            let v = candles.volatility(interval, e)?;
+            Some(format!("{} {:.2}%", e, v * 100.0))
+        })
+        .collect();
+    if !values.is_empty() {
+        println!(
+            "Volatility (annualized, {} candles): {}",
+            interval,
+            values.join(", ")
+        );
+    }
+}
+
+// optionally prints or exports the twap over the trailing n candles at each candle
+fn rolling_series(
+    candles: &candles::CandleList,
+    interval: &candles::Interval,
+    method: &candles::TwapMethod,
+) -> Result<(), Box<dyn Error>> {
+    let window: usize = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Rolling TWAP window in candles (0 to skip)")
+        .default(0)
+        .interact_text()
+        .unwrap();
+    if window == 0 {
+        return Ok(());
+    }
+    let points = candles.rolling(interval, window, method);
+    let outputs = ["Print", "Export CSV"];
+    let output = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Rolling TWAP output")
+        .default(0)
+        .items(&outputs)
+        .interact()
+        .unwrap();
+    if outputs[output] == "Print" {
+        candles::print_rolling(&points);
+    } else {
+        let path: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("File")
+            .default(String::from("rolling_twap.csv"))
+            .interact_text()
+            .unwrap();
+        fs::write(&path, candles::rolling_to_csv(&points))?;
+        println!("Wrote {} points to {}", points.len(), path);
+    }
     Ok(())
 }
+
+fn select_twap_method() -> candles::TwapMethod {
+    let methods = [
+        candles::TwapMethod::Mean,
+        candles::TwapMethod::Median,
+        candles::TwapMethod::TrimmedMean(10.0),
+        candles::TwapMethod::Harmonic,
+    ];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Average candles by")
+        .default(0)
+        .items(&methods)
+        .interact()
+        .unwrap();
+    match methods[selection] {
+        candles::TwapMethod::TrimmedMean(_) => {
+            let pct: f64 = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Percent to trim from each end")
+                .default(10.0)
+                .interact_text()
+                .unwrap();
+            candles::TwapMethod::TrimmedMean(pct)
+        }
+        method => method,
+    }
+}
+
+fn select_candle_options(resolution: candles::Interval) -> candles::CandleOptions {
+    let gap_fills = [
+        candles::GapFill::ForwardFill,
+        candles::GapFill::Empty,
+        candles::GapFill::Interpolate,
+        candles::GapFill::Drop,
+    ];
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Empty candles")
+        .default(0)
+        .items(&gap_fills)
+        .interact()
+        .unwrap();
+    let alignments = [candles::Alignment::Window, candles::Alignment::Epoch];
+    let alignment = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Align candles to")
+        .default(0)
+        .items(&alignments)
+        .interact()
+        .unwrap();
+    let filter_sets = ["None", "Conf, MAD and jump checks"];
+    let filter_set = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Bad tick filters")
+        .default(0)
+        .items(&filter_sets)
+        .interact()
+        .unwrap();
+    let filters = match filter_set {
+        0 => Vec::new(),
+        _ => vec![
+            filter::TickFilter::ConfDeviation(5.0),
+            filter::TickFilter::MedianDeviation {
+                window: 20,
+                threshold: 5.0,
+            },
+            filter::TickFilter::MaxJump(5.0),
+        ],
+    };
+    candles::CandleOptions {
+        gap_fill: gap_fills[selection],
+        alignment: alignments[alignment],
+        resolution,
+        filters,
+    }
+}
+
+fn print_filter_report(options: &candles::CandleOptions, report: &filter::FilterReport) {
+    if !options.filters.is_empty() {
+        println!("Filters: {}", report);
+    }
+}